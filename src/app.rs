@@ -1,10 +1,33 @@
-#[derive(PartialEq, Debug)]
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Local, NaiveDate, TimeDelta};
+use ratatui::widgets::ListState;
+
+use crate::bundles::{blocked_hosts, bundle_hosts, custom_hosts, BUNDLES};
+use crate::error::CommitBlockError;
+use crate::github::{CalendarDay, RateLimit};
+use crate::help::HelpSection;
+use crate::history::UnlockStats;
+use crate::hostname::normalise_host;
+use crate::keys::KeyMap;
+use crate::theme::Theme;
+
+/// How long commits reported by git hooks are counted for, in case GitHub never counts them, e.g. because they were
+/// never pushed
+const LOCAL_COMMIT_EXPIRY_MINUTES: i64 = 60;
+/// How many edits can be undone
+const UNDO_LIMIT: usize = 100;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum CurrentScreen {
     Main,
     Editing,
     Exiting,
     Configuration,
     Help,
+    Token,
+    Bundles,
+    History,
 }
 
 #[derive(PartialEq, Debug)]
@@ -13,6 +36,109 @@ pub enum EditingConfigField {
     GithubUsername,
 }
 
+#[derive(PartialEq, Debug)]
+pub enum TokenField {
+    Token,
+    Passphrase,
+}
+
+/// Whether the configured hosts are currently blocked, as read from the hosts file
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BlockState {
+    Blocked,
+    Unblocked,
+    PartiallyBlocked,
+    NotConfigured,
+}
+
+impl BlockState {
+    /// The block state of hosts with the given enforcement, which is `None` when there are no hosts
+    pub fn of(enforcement: Option<Enforcement>) -> BlockState {
+        match enforcement {
+            Some(Enforcement::Enforced) => BlockState::Blocked,
+            Some(Enforcement::Relaxed) => BlockState::Unblocked,
+            Some(Enforcement::Inconsistent) => BlockState::PartiallyBlocked,
+            None => BlockState::NotConfigured,
+        }
+    }
+}
+
+/// Whether a host's IPv4 and IPv6 entries in the hosts file are active, or `None` if it has no such entry
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct HostState {
+    pub ipv4: Option<bool>,
+    pub ipv6: Option<bool>,
+}
+
+impl HostState {
+    /// Whether the host is blocked, or `None` if only one of its entries is active or either is missing
+    pub fn blocked(&self) -> Option<bool> {
+        match (self.ipv4, self.ipv6) {
+            (Some(ipv4), Some(ipv6)) if ipv4 == ipv6 => Some(ipv4),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the hosts file is actually blocking the hosts, which may not be what's expected after a failed write or an
+/// edit made outside Commit Block
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Enforcement {
+    /// Every host is blocked
+    Enforced,
+    /// Every host is unblocked
+    Relaxed,
+    /// Some hosts are blocked and others aren't, or are missing from the hosts file
+    Inconsistent,
+}
+
+/// Whether the given hosts are blocked according to their entries in the hosts file, or `None` if there are no hosts.
+/// A host missing from the hosts file isn't blocked, and entries for any other hosts are ignored.
+pub fn hosts_enforcement<'a>(host_states: &BTreeMap<String, HostState>, hosts: impl IntoIterator<Item = &'a str>) -> Option<Enforcement> {
+    let blocked: BTreeSet<Option<bool>> = hosts.into_iter()
+        .map(|host| host_states.get(host).copied().unwrap_or_default().blocked())
+        .collect();
+    match Vec::from_iter(blocked).as_slice() {
+        [] => None,
+        [Some(true)] => Some(Enforcement::Enforced),
+        [Some(false)] => Some(Enforcement::Relaxed),
+        _ => Some(Enforcement::Inconsistent),
+    }
+}
+
+/// The hosts and configuration which can be edited, kept so that edits can be undone
+#[derive(PartialEq, Debug, Clone)]
+pub struct Snapshot {
+    pub hosts: Vec<String>,
+    pub bundles: Vec<String>,
+    pub contribution_goal: u32,
+    pub username: String,
+}
+
+/// A connection test which is waiting for GitHub to respond
+#[derive(PartialEq, Debug, Clone)]
+pub struct ConnectionTest {
+    pub username: String,
+    /// Whether the configuration is saved once the user has been found
+    pub save: bool,
+}
+
+/// A message shown in the status panel, along with when it was raised
+#[derive(PartialEq, Debug, Clone)]
+pub struct StatusMessage {
+    pub at: DateTime<Local>,
+    pub message: String,
+}
+
+impl StatusMessage {
+    pub fn now(message: String) -> StatusMessage {
+        StatusMessage {
+            at: Local::now(),
+            message,
+        }
+    }
+}
+
 pub struct App {
     pub host_input: String,
     pub selected_index: usize,
@@ -23,10 +149,78 @@ pub struct App {
     pub github_username_input: String,
     pub editing_config_field: Option<EditingConfigField>,
     pub progress: u32,
+    pub progress_stale: bool,
     pub contribution_goal: u32,
     pub threshold_met_date: Option<String>,
     pub threshold_met_goal: Option<u32>,
     pub username: String,
+    pub dry_run: bool,
+    pub hosts_diff: Option<String>,
+    pub last_fetch: Option<DateTime<Local>>,
+    /// The entries for each host in the hosts file, read regularly so that the file's real state is shown
+    pub host_states: Option<BTreeMap<String, HostState>>,
+    pub next_poll_at: Option<DateTime<Local>>,
+    pub rate_limit: Option<RateLimit>,
+    pub api_error: Option<StatusMessage>,
+    pub hosts_error: Option<StatusMessage>,
+    pub last_error: Option<StatusMessage>,
+    pub token_input: String,
+    pub passphrase_input: String,
+    pub editing_token_field: Option<TokenField>,
+    pub token_status: Option<String>,
+    /// The result of the most recent connection test on the configuration screen
+    pub connection_status: Option<String>,
+    /// A username which GitHub couldn't find, which will be saved anyway if saving is attempted again
+    pub unverified_username: Option<String>,
+    /// The connection test in progress, if any. Only one is run at a time
+    pub connection_test: Option<ConnectionTest>,
+    /// Commits reported by git hooks since GitHub's count was last known to include them all
+    pub local_commits: u32,
+    /// GitHub's count when the first of the local commits was reported
+    pub local_commits_baseline: u32,
+    pub local_commits_since: Option<DateTime<Local>>,
+    /// Names of the enabled bundles, whose hosts are blocked alongside the ones in `hosts`
+    pub bundles: Vec<String>,
+    pub selected_bundle: usize,
+    /// The key bound to each action, from the config file
+    pub keys: KeyMap,
+    pub theme: Theme,
+    /// Why the host being entered couldn't be added, shown beneath it in the editing panel
+    pub host_error: Option<String>,
+    /// The host being edited in place, rather than a new host being added
+    pub editing_host: Option<usize>,
+    /// Position of the cursor in `host_input`, in characters
+    pub host_cursor: usize,
+    /// A host which will be deleted once confirmed
+    pub pending_delete: Option<usize>,
+    pub undo_stack: Vec<Snapshot>,
+    pub redo_stack: Vec<Snapshot>,
+    /// What the hosts were when the editing screen was opened, and how many edits could be undone at that point, so
+    /// that the edits made since can be thrown away
+    pub editing_checkpoint: Option<(Snapshot, usize)>,
+    /// Contributions made on each day of the last year, fetched when the history screen is opened
+    pub calendar: Option<Vec<CalendarDay>>,
+    /// Shown on the history screen while the calendar is being fetched, or if it couldn't be
+    pub calendar_status: Option<String>,
+    pub goal_met_days: BTreeSet<NaiveDate>,
+    /// Today's contribution count at regular intervals, for the history screen
+    pub progress_timeline: Vec<u64>,
+    pub progress_started_at: Option<DateTime<Local>>,
+    pub unlock_stats: Option<UnlockStats>,
+    /// Only hosts containing this are listed, so that a long list can be narrowed down
+    pub host_filter: String,
+    /// Whether the filter is being typed
+    pub filtering_hosts: bool,
+    /// Which part of the list of hosts is scrolled into view
+    pub hosts_list: ListState,
+    /// How many hosts fit in the list at once, which is how far paging up and down moves
+    pub hosts_page_size: usize,
+    pub help_section: HelpSection,
+    /// How many lines of the help section have been scrolled past
+    pub help_scroll: usize,
+    pub help_page_size: usize,
+    /// The screen to go back to once help is closed
+    pub help_return_screen: CurrentScreen,
 }
 
 impl App {
@@ -41,20 +235,389 @@ impl App {
             github_username_input: username.clone(),
             editing_config_field: None,
             progress: current_contributions,
+            progress_stale: false,
             contribution_goal,
             threshold_met_goal,
             threshold_met_date,
             username,
+            dry_run: false,
+            hosts_diff: None,
+            last_fetch: None,
+            host_states: None,
+            next_poll_at: None,
+            rate_limit: None,
+            api_error: None,
+            hosts_error: None,
+            last_error: None,
+            token_input: String::new(),
+            passphrase_input: String::new(),
+            editing_token_field: None,
+            token_status: None,
+            connection_status: None,
+            unverified_username: None,
+            connection_test: None,
+            local_commits: 0,
+            local_commits_baseline: 0,
+            local_commits_since: None,
+            bundles: Vec::new(),
+            selected_bundle: 0,
+            keys: KeyMap::default(),
+            theme: Theme::default(),
+            host_error: None,
+            editing_host: None,
+            host_cursor: 0,
+            pending_delete: None,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            editing_checkpoint: None,
+            calendar: None,
+            calendar_status: None,
+            goal_met_days: BTreeSet::new(),
+            progress_timeline: Vec::new(),
+            progress_started_at: None,
+            unlock_stats: None,
+            host_filter: String::new(),
+            filtering_hosts: false,
+            hosts_list: ListState::default(),
+            hosts_page_size: 0,
+            help_section: HelpSection::Overview,
+            help_scroll: 0,
+            help_page_size: 0,
+            help_return_screen: CurrentScreen::Main,
         }
     }
 
-    pub fn save_new_host(&mut self) {
+    /// Adds the host being entered, or replaces the one being edited, returning false and leaving it to be corrected if
+    /// it isn't a valid hostname or is already blocked
+    pub fn save_new_host(&mut self) -> bool {
         // This prevents a blank entry appearing if you press Enter without typing any hosts
         if !self.host_input.is_empty() {
-            self.hosts.push(self.host_input.clone());
+            match (self.validate_new_host(), self.editing_host) {
+                (Ok(host), Some(index)) => {
+                    if self.hosts[index] != host {
+                        self.record_undo();
+                        self.hosts[index] = host;
+                    }
+                }
+                (Ok(host), None) => {
+                    self.record_undo();
+                    self.hosts.push(host);
+                }
+                (Err(error), _) => {
+                    self.host_error = Some(error);
+                    return false;
+                }
+            }
         }
         self.host_input = String::new();
+        self.host_cursor = 0;
+        self.host_error = None;
+        self.editing_host = None;
+        self.currently_editing = false;
+        true
+    }
+
+    fn validate_new_host(&self) -> Result<String, String> {
+        let host = normalise_host(&self.host_input).map_err(|e| e.to_string())?;
+        let duplicate = self.hosts.iter().enumerate()
+            .any(|(index, existing)| *existing == host && self.editing_host != Some(index));
+        if duplicate {
+            return Err(format!("{} is already blocked", host));
+        }
+        if let Some(bundle) = self.bundles.iter().find(|bundle| bundle_hosts(bundle).contains(&host.as_str())) {
+            return Err(format!("{} is already blocked by the {} bundle", host, bundle));
+        }
+        Ok(host)
+    }
+
+    /// Copies the selected host into the input, so that it can be corrected rather than deleted and typed again
+    pub fn edit_selected_host(&mut self) {
+        if let Some(host) = self.hosts.get(self.selected_index) {
+            self.host_input = host.clone();
+            self.host_cursor = self.host_input.chars().count();
+            self.host_error = None;
+            self.editing_host = Some(self.selected_index);
+        }
+    }
+
+    pub fn delete_host(&mut self, index: usize) {
+        if index < self.hosts.len() {
+            self.record_undo();
+            self.hosts.remove(index);
+        }
+        if self.selected_index >= self.hosts.len() && self.selected_index > 0 {
+            self.selected_index -= 1;
+        }
+    }
+
+    pub fn open_host_editing(&mut self) {
+        self.editing_checkpoint = Some((self.snapshot(), self.undo_stack.len()));
+        self.current_screen = CurrentScreen::Editing;
+        self.currently_editing = true;
+    }
+
+    /// Replaces the hosts with those read from the hosts file, returning whether they were replaced. They're left alone
+    /// while being edited, even with the help open on top, as the edit will be written to the file once it's saved.
+    pub fn replace_hosts_from_file(&mut self, hosts: Vec<String>) -> bool {
+        if self.currently_editing {
+            return false;
+        }
+        self.hosts = custom_hosts(hosts, &self.bundles);
+        self.selected_index = self.selected_index.min(self.hosts.len().saturating_sub(1));
+        true
+    }
+
+    /// Whether the given hosts are blocked in the hosts file. `None` is returned until the file has been read.
+    pub fn enforcement_of<'a>(&self, hosts: impl IntoIterator<Item = &'a str>) -> Option<Enforcement> {
+        hosts_enforcement(self.host_states.as_ref()?, hosts)
+    }
+
+    /// Whether the configured hosts are blocked, as for the enforcement, or `None` until the hosts file has been read
+    pub fn block_state(&self) -> Option<BlockState> {
+        self.host_states.as_ref().map(|_| BlockState::of(self.enforcement()))
+    }
+
+    /// Whether every host, including those in the enabled bundles, is blocked in the hosts file
+    pub fn enforcement(&self) -> Option<Enforcement> {
+        self.enforcement_of(blocked_hosts(&self.hosts, &self.bundles).iter().map(String::as_str))
+    }
+
+    /// Indexes of the hosts which match the filter, in the order they're listed
+    pub fn visible_hosts(&self) -> Vec<usize> {
+        self.hosts.iter().enumerate()
+            .filter(|(_, host)| host.contains(&self.host_filter))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Highlights the host in the given row of the list, counted from the top of the part which is scrolled into view,
+    /// opening the editing screen if it isn't already open. Returns whether there was a host there which could be
+    /// selected.
+    pub fn select_host(&mut self, row: usize) -> bool {
+        let index = match self.visible_hosts().get(self.hosts_list.offset() + row) {
+            Some(index) => *index,
+            None => return false,
+        };
+        if self.editing_host.is_some() || self.pending_delete.is_some() {
+            return false;
+        }
+        if self.current_screen == CurrentScreen::Main {
+            self.open_host_editing();
+        }
+        self.selected_index = index;
+        true
+    }
+
+    /// Moves the highlight up or down the host or bundle list, stopping at either end. As nothing is highlighted on the
+    /// main screen, the list of hosts is scrolled instead.
+    pub fn move_selection(&mut self, offset: isize) {
+        match self.current_screen {
+            CurrentScreen::Main => {
+                let last = self.visible_hosts().len().saturating_sub(self.hosts_page_size);
+                let scroll = self.hosts_list.offset_mut();
+                *scroll = scroll.saturating_add_signed(offset).min(last);
+            }
+            CurrentScreen::Editing if self.editing_host.is_none() && self.pending_delete.is_none() => {
+                let visible = self.visible_hosts();
+                if let Some(last) = visible.len().checked_sub(1) {
+                    // A host which has been filtered out is treated as the next one which hasn't
+                    let position = visible.iter().position(|index| *index >= self.selected_index).unwrap_or(last);
+                    self.selected_index = visible[position.saturating_add_signed(offset).min(last)];
+                }
+            }
+            CurrentScreen::Bundles => {
+                self.selected_bundle = self.selected_bundle.saturating_add_signed(offset).min(BUNDLES.len() - 1);
+            }
+            _ => {}
+        }
+    }
+
+    /// How far paging up or down moves, which is a whole list of hosts
+    pub fn page_size(&self) -> isize {
+        self.hosts_page_size.max(1) as isize
+    }
+
+    pub fn push_host_filter_char(&mut self, c: char) {
+        self.host_filter.extend(c.to_lowercase());
+        self.host_filter_changed();
+    }
+
+    pub fn pop_host_filter_char(&mut self) {
+        self.host_filter.pop();
+        self.host_filter_changed();
+    }
+
+    /// Stops typing the filter, and lists every host again unless it's being kept
+    pub fn close_host_filter(&mut self, keep: bool) {
+        self.filtering_hosts = false;
+        if !keep {
+            self.host_filter.clear();
+            self.host_filter_changed();
+        }
+    }
+
+    /// Scrolls back to the top of the list, and highlights the first host which matches if the highlighted one doesn't
+    fn host_filter_changed(&mut self) {
+        *self.hosts_list.offset_mut() = 0;
+        let visible = self.visible_hosts();
+        if !visible.contains(&self.selected_index) {
+            self.selected_index = visible.first().copied().unwrap_or(0);
+        }
+    }
+
+    /// Opens help on the section for the current screen, or on troubleshooting if something's gone wrong
+    pub fn open_help(&mut self) {
+        self.help_section = match self.current_screen {
+            CurrentScreen::Editing | CurrentScreen::Bundles => HelpSection::BlockedHosts,
+            CurrentScreen::Configuration | CurrentScreen::Token => HelpSection::Configuration,
+            _ if self.hosts_error.is_some() || self.api_error.is_some() => HelpSection::Troubleshooting,
+            _ => HelpSection::Overview,
+        };
+        self.help_scroll = 0;
+        self.help_return_screen = self.current_screen;
+        self.current_screen = CurrentScreen::Help;
+    }
+
+    pub fn close_help(&mut self) {
+        self.current_screen = self.help_return_screen;
+    }
+
+    pub fn move_help_section(&mut self, offset: isize) {
+        self.show_help_section(self.help_section.offset(offset));
+    }
+
+    pub fn show_help_section(&mut self, section: HelpSection) {
+        self.help_section = section;
+        self.help_scroll = 0;
+    }
+
+    /// Scrolls the help section, which is stopped at the end when it's drawn as its length depends on the screen size
+    pub fn scroll_help(&mut self, offset: isize) {
+        self.help_scroll = self.help_scroll.saturating_add_signed(offset);
+    }
+
+    /// Keeps the edits made on the editing screen, which can still be undone afterwards
+    pub fn commit_host_editing(&mut self) {
+        self.editing_checkpoint = None;
+        self.current_screen = CurrentScreen::Main;
+    }
+
+    /// Leaves the editing screen, throwing away whatever was being typed along with any hosts which were added, edited
+    /// or deleted since it was opened
+    pub fn close_host_editing(&mut self) {
+        if let Some((snapshot, undo_depth)) = self.editing_checkpoint.take() {
+            self.restore(snapshot);
+            self.undo_stack.truncate(undo_depth);
+            self.redo_stack.clear();
+        }
+        self.host_input.clear();
+        self.host_cursor = 0;
+        self.host_error = None;
+        self.editing_host = None;
+        self.pending_delete = None;
         self.currently_editing = false;
+        self.current_screen = CurrentScreen::Main;
+    }
+
+    pub fn insert_host_char(&mut self, c: char) {
+        let position = self.host_cursor_byte();
+        self.host_input.insert(position, c);
+        self.host_cursor += 1;
+        self.host_error = None;
+    }
+
+    pub fn delete_host_char_before_cursor(&mut self) {
+        if self.host_cursor > 0 {
+            self.host_cursor -= 1;
+            let position = self.host_cursor_byte();
+            self.host_input.remove(position);
+        }
+        self.host_error = None;
+    }
+
+    pub fn delete_host_char_at_cursor(&mut self) {
+        if self.host_cursor < self.host_input.chars().count() {
+            let position = self.host_cursor_byte();
+            self.host_input.remove(position);
+        }
+        self.host_error = None;
+    }
+
+    /// Moves the cursor to the given character, or to the end of the input if it's past the end
+    pub fn move_host_cursor(&mut self, position: usize) {
+        self.host_cursor = position.min(self.host_input.chars().count());
+    }
+
+    fn host_cursor_byte(&self) -> usize {
+        self.host_input.char_indices()
+            .nth(self.host_cursor)
+            .map_or(self.host_input.len(), |(index, _)| index)
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            hosts: self.hosts.clone(),
+            bundles: self.bundles.clone(),
+            contribution_goal: self.contribution_goal,
+            username: self.username.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.hosts = snapshot.hosts;
+        self.bundles = snapshot.bundles;
+        self.contribution_goal = snapshot.contribution_goal;
+        self.username = snapshot.username;
+        self.contribution_goal_input = self.contribution_goal.to_string();
+        self.github_username_input = self.username.clone();
+        self.selected_index = self.selected_index.min(self.hosts.len().saturating_sub(1));
+    }
+
+    /// Remembers the current hosts and configuration before they're edited, so that the edit can be undone
+    pub fn record_undo(&mut self) {
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.remove(0);
+            if let Some((_, undo_depth)) = &mut self.editing_checkpoint {
+                *undo_depth = undo_depth.saturating_sub(1);
+            }
+        }
+        self.undo_stack.push(self.snapshot());
+        self.redo_stack.clear();
+    }
+
+    /// Reverts the most recent edit, returning whether there was one to revert. On the editing screen, only the edits
+    /// made since it was opened can be undone.
+    pub fn undo(&mut self) -> bool {
+        let undo_depth = self.editing_checkpoint.as_ref().map_or(0, |(_, undo_depth)| *undo_depth);
+        if self.undo_stack.len() <= undo_depth {
+            return false;
+        }
+        let Some(snapshot) = self.undo_stack.pop() else {
+            return false;
+        };
+        self.redo_stack.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    /// Reapplies the most recently undone edit, returning whether there was one
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.redo_stack.pop() else {
+            return false;
+        };
+        self.undo_stack.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    /// Shows the error in the relevant section of the status panel
+    pub fn report_error(&mut self, error: &CommitBlockError) {
+        let status = Some(StatusMessage::now(error.to_string()));
+        match error {
+            CommitBlockError::HostsFile(_) => self.hosts_error = status,
+            e if e.is_api_error() => self.api_error = status,
+            _ => self.last_error = status,
+        }
     }
 
     pub fn toggle_editing_config(&mut self) {
@@ -67,6 +630,68 @@ impl App {
             self.editing_config_field = Some(EditingConfigField::ContributionGoal);
         }
     }
+
+    /// Counts a commit reported by a git hook towards today's progress until GitHub catches up. This only affects
+    /// what's shown, the hosts are only unblocked once GitHub has counted the contributions.
+    pub fn record_local_commit(&mut self, now: DateTime<Local>) {
+        if self.local_commits == 0 {
+            self.local_commits_baseline = self.progress;
+            self.local_commits_since = Some(now);
+        }
+        self.local_commits += 1;
+    }
+
+    /// Drops the local commits once GitHub's count has caught up with them, or once they've expired
+    pub fn reconcile_local_commits(&mut self, now: DateTime<Local>) {
+        let caught_up = self.progress >= self.local_commits_baseline + self.local_commits;
+        let expired = self.local_commits_since
+            .is_some_and(|since| now - since > TimeDelta::minutes(LOCAL_COMMIT_EXPIRY_MINUTES));
+        if caught_up || expired {
+            self.local_commits = 0;
+            self.local_commits_since = None;
+        }
+    }
+
+    /// Today's progress, including any local commits which GitHub hasn't counted yet
+    pub fn displayed_progress(&self) -> u32 {
+        if self.local_commits == 0 {
+            self.progress
+        } else {
+            self.progress.max(self.local_commits_baseline + self.local_commits)
+        }
+    }
+
+    /// How many of the displayed contributions are still waiting to be counted by GitHub
+    pub fn unconfirmed_progress(&self) -> u32 {
+        self.displayed_progress() - self.progress
+    }
+
+    /// Enables the selected bundle if it's disabled, or disables it if it's enabled
+    pub fn toggle_selected_bundle(&mut self) {
+        let Some((name, _)) = BUNDLES.get(self.selected_bundle) else {
+            return;
+        };
+        self.record_undo();
+        match self.bundles.iter().position(|bundle| bundle == name) {
+            Some(index) => { self.bundles.remove(index); }
+            None => self.bundles.push(name.to_string()),
+        }
+    }
+
+    pub fn toggle_editing_token(&mut self) {
+        self.editing_token_field = match self.editing_token_field {
+            Some(TokenField::Token) => Some(TokenField::Passphrase),
+            _ => Some(TokenField::Token),
+        };
+    }
+
+    /// Returns to the main screen, clearing the token and passphrase so they aren't kept in memory
+    pub fn close_token_screen(&mut self) {
+        self.token_input.clear();
+        self.passphrase_input.clear();
+        self.editing_token_field = None;
+        self.current_screen = CurrentScreen::Main;
+    }
 }
 
 #[cfg(test)]
@@ -85,14 +710,14 @@ mod tests {
         let threshold_met_date = Some(String::from_str("01/11/2024").unwrap());
         let threshold_met_goal = Some(4);
 
-        let app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal.clone());
+        let app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal);
 
         assert_eq!(app.host_input, String::new());
         assert_eq!(app.hosts, hosts);
         assert_eq!(app.progress, current_contributions);
         assert_eq!(app.selected_index, 0);
         assert_eq!(app.current_screen, Main);
-        assert_eq!(app.currently_editing, false);
+        assert!(!app.currently_editing);
         assert_eq!(app.contribution_goal_input, contribution_goal.to_string());
         assert_eq!(app.github_username_input, username);
         assert_eq!(app.editing_config_field, None);
@@ -111,12 +736,12 @@ mod tests {
         let threshold_met_date = Some(String::from("01/11/2024"));
         let threshold_met_goal = Some(4);
 
-        let mut app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal.clone());
+        let mut app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal);
 
         app.save_new_host();
 
         assert_eq!(app.host_input, String::new());
-        assert_eq!(app.currently_editing, false);
+        assert!(!app.currently_editing);
         assert_eq!(app.hosts, hosts); // No new hosts saved since host_input is empty
     }
 
@@ -129,14 +754,50 @@ mod tests {
         let threshold_met_date = Some(String::from("01/11/2024"));
         let threshold_met_goal = Some(4);
 
-        let mut app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal.clone());
-        app.host_input = String::from("New Host");
+        let mut app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal);
+        app.host_input = String::from("https://New-Host.example/path");
 
-        app.save_new_host();
+        assert!(app.save_new_host());
 
         assert_eq!(app.host_input, String::new());
-        assert_eq!(app.currently_editing, false);
-        assert_eq!(app.hosts, vec!(String::from("Commit"), String::from("Block"), String::from("New Host")));
+        assert!(!app.currently_editing);
+        assert_eq!(app.hosts, vec!(String::from("Commit"), String::from("Block"), String::from("new-host.example")));
+    }
+
+    #[test]
+    fn save_new_host_rejects_invalid_and_duplicate_hosts() {
+        let mut app = App::new(vec!(String::from("example.com")), 4, 5, String::from("BingBong"), None, None);
+        app.bundles = vec!(String::from("video"));
+        app.currently_editing = true;
+
+        for (input, error) in [
+            ("not a host", "'not a host' isn't a valid hostname"),
+            ("EXAMPLE.com", "example.com is already blocked"),
+            ("youtube.com", "youtube.com is already blocked by the video bundle"),
+        ] {
+            app.host_input = String::from(input);
+            assert!(!app.save_new_host());
+            assert_eq!(app.host_error.as_deref(), Some(error));
+            assert_eq!(app.host_input, input);
+            assert!(app.currently_editing);
+        }
+        assert_eq!(app.hosts, vec!(String::from("example.com")));
+    }
+
+    #[test]
+    fn can_report_errors() {
+        let hosts: Vec<String> = vec!(String::from("Commit"), String::from("Block"));
+        let mut app = App::new(hosts, 4, 5, String::from("BingBong"), None, None);
+        assert_eq!(app.api_error, None);
+        assert_eq!(app.hosts_error, None);
+
+        app.report_error(&CommitBlockError::Unauthorized);
+        app.report_error(&CommitBlockError::HostsFile(std::io::Error::other("read-only file system")));
+        app.report_error(&CommitBlockError::MissingToken);
+
+        assert_eq!(app.api_error.unwrap().message, "No GitHub token found");
+        assert_eq!(app.hosts_error.unwrap().message, "Failed to access hosts file: read-only file system");
+        assert_eq!(app.last_error, None);
     }
 
     #[test]
@@ -148,7 +809,7 @@ mod tests {
         let threshold_met_date = Some(String::from("01/11/2024"));
         let threshold_met_goal = Some(4);
 
-        let mut app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal.clone());
+        let mut app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal);
 
         app.toggle_editing_config();
 
@@ -158,4 +819,338 @@ mod tests {
 
         assert_eq!(app.editing_config_field, Some(GithubUsername));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn local_commits_are_shown_until_github_catches_up() {
+        let mut app = App::new(vec!(), 2, 5, String::from("BingBong"), None, None);
+        let now = Local::now();
+        assert_eq!(app.local_commits, 0);
+        assert_eq!(app.local_commits_baseline, 0);
+        assert_eq!(app.local_commits_since, None);
+
+        app.record_local_commit(now);
+        app.record_local_commit(now);
+        assert_eq!(app.displayed_progress(), 4);
+        assert_eq!(app.unconfirmed_progress(), 2);
+
+        app.progress = 3;
+        app.reconcile_local_commits(now);
+        assert_eq!(app.displayed_progress(), 4);
+        assert_eq!(app.unconfirmed_progress(), 1);
+
+        app.progress = 4;
+        app.reconcile_local_commits(now);
+        assert_eq!(app.local_commits, 0);
+        assert_eq!(app.displayed_progress(), 4);
+    }
+
+    #[test]
+    fn local_commits_expire() {
+        let mut app = App::new(vec!(), 2, 5, String::from("BingBong"), None, None);
+        let now = Local::now();
+        app.record_local_commit(now);
+
+        app.reconcile_local_commits(now + TimeDelta::minutes(LOCAL_COMMIT_EXPIRY_MINUTES + 1));
+
+        assert_eq!(app.displayed_progress(), 2);
+    }
+
+    #[test]
+    fn can_close_token_screen() {
+        let mut app = App::new(vec!(), 4, 5, String::from("BingBong"), None, None);
+        assert_eq!(app.token_status, None);
+        assert_eq!(app.editing_token_field, None);
+        app.current_screen = CurrentScreen::Token;
+        app.toggle_editing_token();
+        app.token_input = String::from("ghp_secret");
+        app.passphrase_input = String::from("correct horse");
+
+        app.close_token_screen();
+
+        assert_eq!(app.current_screen, Main);
+        assert_eq!(app.editing_token_field, None);
+        assert!(app.token_input.is_empty());
+        assert!(app.passphrase_input.is_empty());
+    }
+
+    #[test]
+    fn can_toggle_selected_bundle() {
+        let mut app = App::new(vec!(), 4, 5, String::from("BingBong"), None, None);
+        assert!(app.bundles.is_empty());
+        assert_eq!(app.selected_bundle, 0);
+        app.selected_bundle = 2;
+
+        app.toggle_selected_bundle();
+        assert_eq!(app.bundles, vec!(String::from("video")));
+
+        app.toggle_selected_bundle();
+        assert!(app.bundles.is_empty());
+    }
+
+    #[test]
+    fn can_edit_selected_host() {
+        let mut app = App::new(vec!(String::from("exmaple.com"), String::from("example.org")), 4, 5, String::from("BingBong"), None, None);
+        assert_eq!(app.editing_host, None);
+        assert_eq!(app.host_cursor, 0);
+        app.currently_editing = true;
+        app.edit_selected_host();
+        assert_eq!(app.host_cursor, 11);
+
+        app.move_host_cursor(2);
+        app.delete_host_char_at_cursor();
+        app.move_host_cursor(3);
+        app.insert_host_char('m');
+        assert_eq!(app.host_input, "example.com");
+
+        assert!(app.save_new_host());
+        assert_eq!(app.hosts, vec!(String::from("example.com"), String::from("example.org")));
+        assert_eq!(app.editing_host, None);
+    }
+
+    #[test]
+    fn edited_host_can_keep_its_name_but_not_duplicate_another() {
+        let mut app = App::new(vec!(String::from("example.com"), String::from("example.org")), 4, 5, String::from("BingBong"), None, None);
+        app.edit_selected_host();
+        assert!(app.save_new_host());
+
+        app.edit_selected_host();
+        app.move_host_cursor(usize::MAX);
+        app.delete_host_char_before_cursor();
+        app.delete_host_char_before_cursor();
+        app.delete_host_char_before_cursor();
+        "org".chars().for_each(|c| app.insert_host_char(c));
+
+        assert_eq!(app.host_error, None);
+
+        assert!(!app.save_new_host());
+        assert_eq!(app.host_error.as_deref(), Some("example.org is already blocked"));
+    }
+
+    #[test]
+    fn can_delete_host() {
+        let mut app = App::new(vec!(String::from("example.com"), String::from("example.org")), 4, 5, String::from("BingBong"), None, None);
+        app.selected_index = 1;
+
+        app.delete_host(1);
+
+        assert_eq!(app.hosts, vec!(String::from("example.com")));
+        assert_eq!(app.selected_index, 0);
+    }
+
+    #[test]
+    fn can_undo_and_redo_edits() {
+        let mut app = App::new(vec!(String::from("example.com")), 4, 5, String::from("BingBong"), None, None);
+        assert!(app.undo_stack.is_empty());
+        assert!(app.redo_stack.is_empty());
+        app.open_host_editing();
+        app.host_input = String::from("example.org");
+        app.save_new_host();
+        app.commit_host_editing();
+        app.toggle_selected_bundle();
+
+        assert!(app.undo());
+        assert!(app.bundles.is_empty());
+        assert!(app.undo());
+        assert_eq!(app.hosts, vec!(String::from("example.com")));
+        assert!(!app.undo());
+
+        assert!(app.redo());
+        assert_eq!(app.hosts, vec!(String::from("example.com"), String::from("example.org")));
+        app.delete_host(0);
+        assert!(!app.redo());
+    }
+
+    #[test]
+    fn closing_host_editing_discards_edits() {
+        let mut app = App::new(vec!(String::from("example.com"), String::from("example.org")), 4, 5, String::from("BingBong"), None, None);
+        app.record_undo();
+        app.open_host_editing();
+        app.delete_host(0);
+        app.edit_selected_host();
+        app.insert_host_char('x');
+        assert!(app.save_new_host());
+
+        assert!(app.undo());
+        assert_eq!(app.hosts, vec!(String::from("example.org")));
+        app.close_host_editing();
+
+        assert_eq!(app.hosts, vec!(String::from("example.com"), String::from("example.org")));
+        assert_eq!(app.undo_stack.len(), 1);
+        assert!(app.redo_stack.is_empty());
+        assert_eq!(app.current_screen, Main);
+    }
+
+    #[test]
+    fn undo_on_editing_screen_stops_at_checkpoint() {
+        let mut app = App::new(vec!(String::from("example.com")), 4, 5, String::from("BingBong"), None, None);
+        assert_eq!(app.editing_checkpoint, None);
+        app.toggle_selected_bundle();
+        app.open_host_editing();
+
+        assert!(!app.undo());
+        assert_eq!(app.bundles, vec!(String::from("social")));
+    }
+
+    #[test]
+    fn clicking_host_opens_editing_screen() {
+        let mut app = App::new(vec!(String::from("example.com"), String::from("example.org")), 4, 5, String::from("BingBong"), None, None);
+
+        assert!(!app.select_host(2));
+        assert_eq!(app.current_screen, Main);
+        assert!(app.select_host(1));
+
+        assert_eq!(app.current_screen, CurrentScreen::Editing);
+        assert_eq!(app.selected_index, 1);
+
+        // Clicks are ignored while a delete is waiting to be confirmed
+        assert_eq!(app.pending_delete, None);
+        app.pending_delete = Some(1);
+        assert!(!app.select_host(0));
+        assert_eq!(app.selected_index, 1);
+    }
+
+    #[test]
+    fn moving_selection_stops_at_ends() {
+        let mut app = App::new(vec!(String::from("example.com"), String::from("example.org")), 4, 5, String::from("BingBong"), None, None);
+        app.open_host_editing();
+
+        app.move_selection(-1);
+        assert_eq!(app.selected_index, 0);
+        app.move_selection(5);
+        assert_eq!(app.selected_index, 1);
+
+        app.current_screen = CurrentScreen::Bundles;
+        app.move_selection(-3);
+        assert_eq!(app.selected_bundle, 0);
+        app.move_selection(100);
+        assert_eq!(app.selected_bundle, BUNDLES.len() - 1);
+    }
+
+    #[test]
+    fn filter_narrows_hosts_and_selection() {
+        let hosts = ["example.com", "news.example.org", "example.net", "news.example.net"].map(String::from).to_vec();
+        let mut app = App::new(hosts, 4, 5, String::from("BingBong"), None, None);
+        assert_eq!(app.host_filter, "");
+        assert!(!app.filtering_hosts);
+        app.open_host_editing();
+        app.selected_index = 2;
+
+        for c in "NEWS".chars() {
+            app.push_host_filter_char(c);
+        }
+        assert_eq!(app.visible_hosts(), vec!(1, 3));
+        assert_eq!(app.selected_index, 1);
+        app.move_selection(1);
+        assert_eq!(app.selected_index, 3);
+        app.move_selection(1);
+        assert_eq!(app.selected_index, 3);
+
+        app.close_host_filter(true);
+        assert_eq!(app.host_filter, "news");
+        app.close_host_filter(false);
+        assert_eq!(app.visible_hosts().len(), 4);
+        assert_eq!(app.selected_index, 3);
+    }
+
+    #[test]
+    fn main_screen_scrolls_hosts_a_page_at_a_time() {
+        let hosts = (0..50).map(|i| format!("example{}.com", i)).collect();
+        let mut app = App::new(hosts, 4, 5, String::from("BingBong"), None, None);
+        assert_eq!(app.hosts_list, ListState::default());
+        assert_eq!(app.hosts_page_size, 0);
+        app.hosts_page_size = 20;
+
+        app.move_selection(app.page_size());
+        assert_eq!(app.hosts_list.offset(), 20);
+        app.move_selection(app.page_size());
+        assert_eq!(app.hosts_list.offset(), 30);
+        assert!(app.select_host(2));
+        assert_eq!(app.selected_index, 32);
+    }
+
+    #[test]
+    fn help_opens_on_section_for_current_screen() {
+        let mut app = App::new(vec!(String::from("example.com")), 4, 5, String::from("BingBong"), None, None);
+        assert_eq!(app.help_section, HelpSection::Overview);
+        assert_eq!(app.help_scroll, 0);
+        assert_eq!(app.help_page_size, 0);
+        app.open_host_editing();
+
+        app.open_help();
+        assert_eq!(app.help_section, HelpSection::BlockedHosts);
+        app.scroll_help(5);
+        app.move_help_section(1);
+        assert_eq!(app.help_section, HelpSection::Configuration);
+        assert_eq!(app.help_scroll, 0);
+        assert_eq!(app.help_return_screen, CurrentScreen::Editing);
+        app.close_help();
+        assert_eq!(app.current_screen, CurrentScreen::Editing);
+
+        app.close_host_editing();
+        app.hosts_error = Some(StatusMessage::now(String::from("Permission denied")));
+        app.open_help();
+        assert_eq!(app.help_section, HelpSection::Troubleshooting);
+    }
+
+    #[test]
+    fn hosts_from_file_wait_for_editing_to_finish() {
+        let mut app = App::new(vec!(String::from("example.com")), 4, 5, String::from("BingBong"), None, None);
+        app.open_host_editing();
+        app.open_help();
+
+        assert!(!app.replace_hosts_from_file(vec!(String::from("example.org"))));
+        assert_eq!(app.hosts, vec!(String::from("example.com")));
+
+        app.close_help();
+        app.close_host_editing();
+        assert!(app.replace_hosts_from_file(vec!(String::from("example.org"))));
+        assert_eq!(app.hosts, vec!(String::from("example.org")));
+    }
+
+    #[test]
+    fn enforcement_reflects_hosts_file() {
+        let mut app = App::new(vec!(String::from("example.com"), String::from("example.org")), 4, 5, String::from("BingBong"), None, None);
+        assert_eq!(app.host_states, None);
+        assert_eq!(app.enforcement(), None);
+
+        let blocked = HostState { ipv4: Some(true), ipv6: Some(true) };
+        app.host_states = Some(BTreeMap::from([(String::from("example.com"), blocked), (String::from("example.org"), blocked)]));
+        assert_eq!(app.enforcement(), Some(Enforcement::Enforced));
+
+        let unblocked = HostState { ipv4: Some(false), ipv6: Some(false) };
+        app.host_states = Some(BTreeMap::from([(String::from("example.com"), unblocked), (String::from("example.org"), unblocked)]));
+        assert_eq!(app.enforcement(), Some(Enforcement::Relaxed));
+
+        let half_blocked = HostState { ipv4: Some(true), ipv6: Some(false) };
+        app.host_states = Some(BTreeMap::from([(String::from("example.com"), unblocked), (String::from("example.org"), half_blocked)]));
+        assert_eq!(app.enforcement(), Some(Enforcement::Inconsistent));
+        assert_eq!(app.enforcement_of(["example.com"]), Some(Enforcement::Relaxed));
+
+        // A host missing from the hosts file isn't blocked
+        app.host_states = Some(BTreeMap::from([(String::from("example.com"), blocked)]));
+        assert_eq!(app.enforcement(), Some(Enforcement::Inconsistent));
+    }
+
+    #[test]
+    fn block_state_agrees_with_enforcement() {
+        let mut app = App::new(vec!(String::from("example.com"), String::from("example.org")), 4, 5, String::from("BingBong"), None, None);
+        assert_eq!(app.block_state(), None);
+
+        let blocked = HostState { ipv4: Some(true), ipv6: Some(true) };
+        let unblocked = HostState { ipv4: Some(false), ipv6: Some(false) };
+        // example.org is configured but missing from the hosts file
+        app.host_states = Some(BTreeMap::from([(String::from("example.com"), blocked)]));
+        assert_eq!(app.enforcement(), Some(Enforcement::Inconsistent));
+        assert_eq!(app.block_state(), Some(BlockState::PartiallyBlocked));
+
+        // Entries left behind for a host which is no longer configured are ignored
+        app.host_states = Some(BTreeMap::from([
+            (String::from("example.com"), blocked), (String::from("example.org"), blocked), (String::from("removed.com"), unblocked),
+        ]));
+        assert_eq!(app.enforcement(), Some(Enforcement::Enforced));
+        assert_eq!(app.block_state(), Some(BlockState::Blocked));
+
+        app.hosts.clear();
+        assert_eq!(app.block_state(), Some(BlockState::NotConfigured));
+    }
+}
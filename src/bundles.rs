@@ -0,0 +1,87 @@
+/// Curated lists of hosts which can be blocked as a whole. Only the name of an enabled bundle is stored in the config,
+/// so any hosts added to a bundle in later versions are blocked too.
+pub const BUNDLES: [(&str, &[&str]); 4] = [
+    ("social", &[
+        "facebook.com", "www.facebook.com",
+        "instagram.com", "www.instagram.com",
+        "twitter.com", "www.twitter.com",
+        "x.com", "www.x.com",
+        "reddit.com", "www.reddit.com", "old.reddit.com",
+        "tiktok.com", "www.tiktok.com",
+        "linkedin.com", "www.linkedin.com",
+    ]),
+    ("news", &[
+        "news.ycombinator.com",
+        "bbc.co.uk", "www.bbc.co.uk",
+        "bbc.com", "www.bbc.com",
+        "cnn.com", "www.cnn.com",
+        "nytimes.com", "www.nytimes.com",
+        "theguardian.com", "www.theguardian.com",
+    ]),
+    ("video", &[
+        "youtube.com", "www.youtube.com", "m.youtube.com",
+        "netflix.com", "www.netflix.com",
+        "twitch.tv", "www.twitch.tv",
+        "vimeo.com", "www.vimeo.com",
+    ]),
+    ("shopping", &[
+        "amazon.com", "www.amazon.com",
+        "ebay.com", "www.ebay.com",
+        "etsy.com", "www.etsy.com",
+        "aliexpress.com", "www.aliexpress.com",
+    ]),
+];
+
+pub fn bundle_hosts(name: &str) -> &'static [&'static str] {
+    BUNDLES.iter()
+        .find(|(bundle_name, _)| *bundle_name == name)
+        .map(|(_, hosts)| *hosts)
+        .unwrap_or_default()
+}
+
+/// Every host to block: the hosts added individually, followed by those in each enabled bundle
+pub fn blocked_hosts(hosts: &[String], enabled_bundles: &[String]) -> Vec<String> {
+    let mut blocked_hosts = hosts.to_vec();
+    for host in enabled_bundles.iter().flat_map(|bundle| bundle_hosts(bundle)) {
+        if !blocked_hosts.iter().any(|blocked_host| blocked_host == host) {
+            blocked_hosts.push(host.to_string());
+        }
+    }
+    blocked_hosts
+}
+
+/// The hosts which were added individually, leaving out those which are blocked as part of an enabled bundle
+pub fn custom_hosts(blocked_hosts: Vec<String>, enabled_bundles: &[String]) -> Vec<String> {
+    blocked_hosts.into_iter()
+        .filter(|host| !enabled_bundles.iter().any(|bundle| bundle_hosts(bundle).contains(&host.as_str())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_bundle_has_no_hosts() {
+        assert!(bundle_hosts("gardening").is_empty());
+        assert!(bundle_hosts("video").contains(&"youtube.com"));
+    }
+
+    #[test]
+    fn blocked_hosts_include_bundles_once() {
+        let hosts = vec!(String::from("example.com"), String::from("youtube.com"));
+
+        let blocked_hosts = blocked_hosts(&hosts, &[String::from("video")]);
+
+        assert_eq!(blocked_hosts[..3], [String::from("example.com"), String::from("youtube.com"), String::from("www.youtube.com")]);
+        assert_eq!(blocked_hosts.iter().filter(|host| *host == "youtube.com").count(), 1);
+    }
+
+    #[test]
+    fn custom_hosts_leave_out_bundles() {
+        let enabled_bundles = [String::from("news")];
+        let blocked_hosts = blocked_hosts(&[String::from("example.com")], &enabled_bundles);
+
+        assert_eq!(custom_hosts(blocked_hosts, &enabled_bundles), vec!(String::from("example.com")));
+    }
+}
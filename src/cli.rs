@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::host_list::HostListFormat;
+use crate::HOST_FILE_PATH;
+
+/// Block distracting hosts until today's GitHub contribution goal has been met
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Args {
+    /// Leave the hosts file untouched and preview the changes which would have been made instead
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Path to the configuration file [default: $XDG_CONFIG_HOME/commit-block/config.toml, or /etc/commit-block/config.toml as root]
+    #[arg(long, env = "COMMIT_BLOCK_CONFIG", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Directory in which to keep state between runs [default: $XDG_STATE_HOME/commit-block, or /var/lib/commit-block as root]
+    #[arg(long, env = "COMMIT_BLOCK_STATE_DIR", value_name = "DIR")]
+    pub state_dir: Option<PathBuf>,
+
+    /// Path to the hosts file which is modified to block hosts
+    #[arg(long, env = "COMMIT_BLOCK_HOSTS_FILE", value_name = "FILE", default_value = HOST_FILE_PATH)]
+    pub hosts_file: PathBuf,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Commands which are sent to an already running instance, rather than starting the interface
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check contribution progress now, rather than waiting for the next scheduled check
+    Refresh,
+    /// Manage the git hooks which report commits as soon as they're made
+    Hooks {
+        #[command(subcommand)]
+        command: HooksCommand,
+    },
+    /// Import and export the list of blocked hosts
+    Hosts {
+        #[command(subcommand)]
+        command: HostsCommand,
+    },
+    /// Export the recorded contribution progress and unlock times
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// Report a git event to the running instance. This is run by the installed hooks
+    #[command(hide = true)]
+    Notify {
+        event: HookEvent,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HooksCommand {
+    /// Install post-commit and pre-push hooks into a repository
+    Install {
+        /// Path to the repository
+        #[arg(default_value = ".")]
+        repo: PathBuf,
+
+        /// Replace existing hooks, keeping a backup of each
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HostsCommand {
+    /// Add the hosts in a file to the blocked hosts. Hosts which are already blocked are skipped
+    Import {
+        /// File containing the hosts to import
+        file: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = HostListFormat::Auto)]
+        format: HostListFormat,
+    },
+    /// Write the blocked hosts, other than those in enabled bundles, to a file or stdout
+    Export {
+        /// File to write the hosts to. They're written to stdout if this isn't given
+        file: Option<PathBuf>,
+
+        #[arg(long, value_enum, default_value_t = HostListFormat::Text)]
+        format: HostListFormat,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistoryCommand {
+    /// Write every recorded change in progress and every unlock as CSV, to a file or stdout
+    Export {
+        /// File to write the CSV to. It's written to stdout if this isn't given
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum HookEvent {
+    Commit,
+    Push,
+}
@@ -0,0 +1,286 @@
+use std::{env, fs, io};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UnixListener};
+use tokio_util::sync::CancellationToken;
+
+use crate::error::CommitBlockError;
+
+const REFRESH_COMMAND: &str = "refresh";
+const COMMIT_COMMAND: &str = "commit";
+const PUSH_COMMAND: &str = "push";
+const OK_RESPONSE: &str = "ok";
+const ERROR_RESPONSE_PREFIX: &str = "error: ";
+const WEBHOOK_REFRESH_PATH: &str = "/refresh";
+/// Connections which don't send a complete request within this time are dropped
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+/// Webhook requests with headers larger than this are rejected
+const MAX_WEBHOOK_HEADER_BYTES: usize = 8 * 1024;
+
+/// Commands which can be sent to the running application over its socket
+#[derive(Debug, PartialEq)]
+pub enum DaemonCommand {
+    /// Check contribution progress straight away, rather than waiting for the next poll
+    Refresh,
+    /// A commit has been made locally, which GitHub won't have counted yet
+    Commit,
+    /// Commits are about to be pushed
+    Push,
+}
+
+impl DaemonCommand {
+    fn parse(line: &str) -> Option<DaemonCommand> {
+        match line.trim() {
+            REFRESH_COMMAND => Some(DaemonCommand::Refresh),
+            COMMIT_COMMAND => Some(DaemonCommand::Commit),
+            PUSH_COMMAND => Some(DaemonCommand::Push),
+            _ => None,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        let command = match self {
+            DaemonCommand::Refresh => REFRESH_COMMAND,
+            DaemonCommand::Commit => COMMIT_COMMAND,
+            DaemonCommand::Push => PUSH_COMMAND,
+        };
+        format!("{}\n", command)
+    }
+}
+
+/// Acts on the commands received by the running application
+pub type CommandHandler = Arc<dyn Fn(DaemonCommand) + Send + Sync>;
+
+/// Sends a command to the running application, waiting for it to be acknowledged
+pub fn send_command(socket_file: &Path, command: &DaemonCommand) -> Result<(), CommitBlockError> {
+    let mut stream = UnixStream::connect(socket_file).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused => CommitBlockError::NotRunning(socket_file.to_path_buf()),
+        _ => CommitBlockError::Io(e),
+    })?;
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.write_all(command.to_line().as_bytes())?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    match response.trim() {
+        OK_RESPONSE => Ok(()),
+        response => Err(CommitBlockError::Daemon(response.trim_start_matches(ERROR_RESPONSE_PREFIX).to_string())),
+    }
+}
+
+/// Listens on the socket which the `commit-block` subcommands and git hooks connect to. A socket left behind by a
+/// previous run is replaced, but not one which another instance is still listening on. Only the owner can use the
+/// socket; when run with `sudo` it's handed to the user who ran `sudo`, so that their git hooks can still reach it.
+pub fn bind_socket(socket_file: &Path) -> Result<UnixListener, CommitBlockError> {
+    if socket_file.exists() {
+        if UnixStream::connect(socket_file).is_ok() {
+            return Err(CommitBlockError::AlreadyRunning(socket_file.to_path_buf()));
+        }
+        fs::remove_file(socket_file)?;
+    }
+    let listener = UnixListener::bind(socket_file)?;
+    fs::set_permissions(socket_file, fs::Permissions::from_mode(0o600))?;
+    if let Some(uid) = sudo_uid() {
+        std::os::unix::fs::chown(socket_file, Some(uid), None)?;
+    }
+    Ok(listener)
+}
+
+/// The ID of the user who ran `sudo`, if the application was started with it
+fn sudo_uid() -> Option<u32> {
+    env::var("SUDO_UID").ok()?.parse().ok()
+}
+
+/// Handles commands sent to the socket until shutdown is requested, removing the socket afterwards
+pub async fn serve_socket(listener: UnixListener, socket_file: &Path, handler: CommandHandler, shutdown: CancellationToken) {
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            accepted = listener.accept() => {
+                if let Ok((stream, _)) = accepted {
+                    tokio::spawn(handle_socket_connection(stream, Arc::clone(&handler)));
+                }
+            }
+        }
+    }
+    let _ = fs::remove_file(socket_file);
+}
+
+async fn handle_socket_connection(stream: tokio::net::UnixStream, handler: CommandHandler) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+
+    while let Ok(Ok(Some(line))) = tokio::time::timeout(CONNECTION_TIMEOUT, lines.next_line()).await {
+        let response = match DaemonCommand::parse(&line) {
+            Some(command) => {
+                handler(command);
+                OK_RESPONSE.to_string()
+            }
+            None => format!("{}unknown command '{}'", ERROR_RESPONSE_PREFIX, line.trim()),
+        };
+        if writer.write_all(format!("{}\n", response).as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Serves a minimal HTTP endpoint, so that a git hook or a relay for a forge's webhooks can trigger a refresh with
+/// `POST /refresh`
+pub async fn serve_webhook(listener: TcpListener, handler: CommandHandler, shutdown: CancellationToken) {
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            accepted = listener.accept() => {
+                if let Ok((stream, _)) = accepted {
+                    tokio::spawn(handle_webhook_connection(stream, Arc::clone(&handler)));
+                }
+            }
+        }
+    }
+}
+
+async fn handle_webhook_connection(mut stream: tokio::net::TcpStream, handler: CommandHandler) {
+    let Ok(Ok(request)) = tokio::time::timeout(CONNECTION_TIMEOUT, read_request_head(&mut stream)).await else {
+        return;
+    };
+
+    let status = match request.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+        ["POST", WEBHOOK_REFRESH_PATH] => {
+            handler(DaemonCommand::Refresh);
+            "204 No Content"
+        }
+        [_, WEBHOOK_REFRESH_PATH] => "405 Method Not Allowed",
+        [_, _] => "404 Not Found",
+        _ => "400 Bad Request",
+    };
+    let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+/// Reads the request line and headers. Any body is ignored, since the request itself is the signal to refresh.
+async fn read_request_head(stream: &mut tokio::net::TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 || head.len() + read > MAX_WEBHOOK_HEADER_BYTES {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Incomplete request"));
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use tokio::net::TcpStream;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn socket_file(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("commit-block-{}-{}.sock", name, std::process::id()))
+    }
+
+    #[test]
+    fn can_parse_commands() {
+        assert_eq!(DaemonCommand::parse("refresh\n"), Some(DaemonCommand::Refresh));
+        assert_eq!(DaemonCommand::parse(&DaemonCommand::Refresh.to_line()), Some(DaemonCommand::Refresh));
+        assert_eq!(DaemonCommand::parse(&DaemonCommand::Commit.to_line()), Some(DaemonCommand::Commit));
+        assert_eq!(DaemonCommand::parse(&DaemonCommand::Push.to_line()), Some(DaemonCommand::Push));
+        assert_eq!(DaemonCommand::parse("reboot"), None);
+    }
+
+    #[test]
+    fn send_command_not_running() {
+        let socket_file = socket_file("not-running");
+
+        let result = send_command(&socket_file, &DaemonCommand::Refresh);
+
+        assert!(matches!(result, Err(CommitBlockError::NotRunning(_))));
+    }
+
+    fn channel_handler() -> (CommandHandler, mpsc::UnboundedReceiver<DaemonCommand>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Arc::new(move |command| { let _ = tx.send(command); }), rx)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn socket_passes_commands_to_handler() {
+        let socket_file = socket_file("refresh");
+        let (handler, mut commands) = channel_handler();
+        let shutdown = CancellationToken::new();
+        let listener = bind_socket(&socket_file).unwrap();
+        let server = tokio::spawn({
+            let socket_file = socket_file.clone();
+            let shutdown = shutdown.clone();
+            async move { serve_socket(listener, &socket_file, handler, shutdown).await }
+        });
+
+        let client_socket_file = socket_file.clone();
+        tokio::task::spawn_blocking(move || send_command(&client_socket_file, &DaemonCommand::Commit)).await.unwrap().unwrap();
+        assert_eq!(commands.recv().await, Some(DaemonCommand::Commit));
+
+        shutdown.cancel();
+        server.await.unwrap();
+        assert!(!socket_file.exists());
+    }
+
+    #[tokio::test]
+    async fn socket_only_accessible_to_owner() {
+        let socket_file = socket_file("permissions");
+
+        let _listener = bind_socket(&socket_file).unwrap();
+
+        assert_eq!(fs::metadata(&socket_file).unwrap().permissions().mode() & 0o777, 0o600);
+        fs::remove_file(&socket_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn bind_socket_replaces_stale_socket_only() {
+        let socket_file = socket_file("stale");
+        // Dropping a listener leaves its socket behind, as happens when the application is killed
+        drop(bind_socket(&socket_file).unwrap());
+        assert!(socket_file.exists());
+
+        let _listener = bind_socket(&socket_file).unwrap();
+
+        assert!(matches!(bind_socket(&socket_file), Err(CommitBlockError::AlreadyRunning(_))));
+        assert!(socket_file.exists());
+        fs::remove_file(&socket_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn webhook_refresh() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (handler, mut commands) = channel_handler();
+        let shutdown = CancellationToken::new();
+        tokio::spawn(serve_webhook(listener, handler, shutdown.clone()));
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_all(b"POST /refresh HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 204 No Content"));
+        assert_eq!(commands.recv().await, Some(DaemonCommand::Refresh));
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_all(b"GET /refresh HTTP/1.1\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed"));
+        shutdown.cancel();
+    }
+}
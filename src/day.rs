@@ -0,0 +1,111 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Works out which day contributions count towards. Days are measured in the configured timezone, or the system one
+/// if none is set, and can start later than midnight for those who work late into the night.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ContributionDay {
+    timezone: Option<Tz>,
+    day_start: NaiveTime,
+}
+
+impl ContributionDay {
+    pub fn new(timezone: Option<Tz>, day_start: Option<NaiveTime>) -> ContributionDay {
+        ContributionDay {
+            timezone,
+            day_start: day_start.unwrap_or(NaiveTime::MIN),
+        }
+    }
+
+    /// The local time at which each day starts
+    pub fn day_start(&self) -> NaiveTime {
+        self.day_start
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.date_at(Utc::now())
+    }
+
+    /// The day which contributions made at the given instant count towards
+    pub fn date_at(&self, instant: DateTime<Utc>) -> NaiveDate {
+        (self.local_time(instant) - (self.day_start - NaiveTime::MIN)).date()
+    }
+
+    /// The wall clock time at the given instant, in the timezone days are measured in
+    pub fn local_time(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        match self.timezone {
+            Some(timezone) => instant.with_timezone(&timezone).naive_local(),
+            None => instant.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    /// The start and end of the given day. The end is the start of the following day.
+    pub fn bounds(&self, date: NaiveDate) -> (DateTime<FixedOffset>, DateTime<FixedOffset>) {
+        (self.start_of(date), self.start_of(date + TimeDelta::days(1)))
+    }
+
+    fn start_of(&self, date: NaiveDate) -> DateTime<FixedOffset> {
+        let start = date.and_time(self.day_start);
+        match self.timezone {
+            Some(timezone) => to_fixed_offset(&timezone, start),
+            None => to_fixed_offset(&Local, start),
+        }
+    }
+}
+
+/// Finds the instant for a local time. Times which are skipped when the clocks go forward are moved on by an hour,
+/// and for times which happen twice when the clocks go back, the earlier is used.
+fn to_fixed_offset<T: TimeZone>(timezone: &T, local: NaiveDateTime) -> DateTime<FixedOffset> {
+    timezone.from_local_datetime(&local).earliest()
+        .or_else(|| timezone.from_local_datetime(&(local + TimeDelta::hours(1))).earliest())
+        .map(|instant| instant.fixed_offset())
+        .unwrap_or_else(|| local.and_utc().fixed_offset())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instant(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().to_utc()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn date_at_uses_timezone() {
+        let day = ContributionDay::new(Some(Tz::Pacific__Auckland), None);
+
+        assert_eq!(day.date_at(instant("2024-10-26T23:00:00Z")), date("2024-10-27"));
+    }
+
+    #[test]
+    fn date_at_uses_day_start() {
+        let day = ContributionDay::new(Some(Tz::Europe__London), NaiveTime::from_hms_opt(4, 0, 0));
+
+        assert_eq!(day.date_at(instant("2024-10-27T02:30:00Z")), date("2024-10-26"));
+        assert_eq!(day.date_at(instant("2024-10-27T04:30:00Z")), date("2024-10-27"));
+    }
+
+    #[test]
+    fn bounds_use_timezone_and_day_start() {
+        let day = ContributionDay::new(Some(Tz::America__New_York), NaiveTime::from_hms_opt(4, 0, 0));
+
+        let (from, to) = day.bounds(date("2024-07-01"));
+
+        assert_eq!(from.to_rfc3339(), "2024-07-01T04:00:00-04:00");
+        assert_eq!(to.to_rfc3339(), "2024-07-02T04:00:00-04:00");
+    }
+
+    #[test]
+    fn bounds_skip_missing_local_time() {
+        // The clocks go forward from 02:00 to 03:00 on this day
+        let day = ContributionDay::new(Some(Tz::Europe__Berlin), NaiveTime::from_hms_opt(2, 30, 0));
+
+        let (from, _) = day.bounds(date("2024-03-31"));
+
+        assert_eq!(from.to_rfc3339(), "2024-03-31T03:30:00+02:00");
+    }
+}
@@ -0,0 +1,178 @@
+use std::{fmt, io};
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local, Utc};
+
+/// Errors which can occur while checking contribution progress or updating the hosts file
+#[derive(Debug)]
+pub enum CommitBlockError {
+    Io(io::Error),
+    Http(reqwest::Error),
+    Json(serde_json::Error),
+    ConfigParse(toml::de::Error),
+    ConfigSerialize(toml::ser::Error),
+    StateParse(chrono::ParseError),
+    HostsFile(io::Error),
+    InsecureTokenFile(PathBuf),
+    Secrets(String),
+    NotRunning(PathBuf),
+    AlreadyRunning(PathBuf),
+    Daemon(String),
+    Hooks(String),
+    HostList(String),
+    InvalidHost(String),
+    KeyBindings(String),
+    NoHomeDirectory,
+    MissingToken,
+    Unauthorized,
+    RateLimited {
+        reset_at: Option<DateTime<Utc>>,
+    },
+    UserNotFound,
+    Api(String),
+}
+
+impl CommitBlockError {
+    /// Whether the error was raised while fetching contributions from GitHub
+    pub fn is_api_error(&self) -> bool {
+        matches!(self,
+            CommitBlockError::Http(_)
+            | CommitBlockError::Json(_)
+            | CommitBlockError::MissingToken
+            | CommitBlockError::Unauthorized
+            | CommitBlockError::RateLimited { .. }
+            | CommitBlockError::UserNotFound
+            | CommitBlockError::Api(_))
+    }
+}
+
+impl fmt::Display for CommitBlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommitBlockError::Io(e) => write!(f, "{}", e),
+            CommitBlockError::Http(e) => write!(f, "Request to GitHub failed: {}", e),
+            CommitBlockError::Json(e) => write!(f, "Failed to parse GitHub response: {}", e),
+            CommitBlockError::ConfigParse(e) => write!(f, "Failed to parse config file: {}", e),
+            CommitBlockError::ConfigSerialize(e) => write!(f, "Failed to serialize config: {}", e),
+            CommitBlockError::StateParse(e) => write!(f, "Failed to parse state file: {}", e),
+            CommitBlockError::HostsFile(e) => write!(f, "Failed to access hosts file: {}", e),
+            CommitBlockError::InsecureTokenFile(path) => {
+                write!(f, "Refusing to read token file {} as other users have access to it", path.display())
+            }
+            CommitBlockError::Secrets(message) => write!(f, "Failed to read secrets: {}", message),
+            CommitBlockError::NotRunning(socket_file) => {
+                write!(f, "Commit Block doesn't appear to be running, as nothing is listening on {}", socket_file.display())
+            }
+            CommitBlockError::AlreadyRunning(socket_file) => {
+                write!(f, "Another instance of Commit Block is already listening on {}", socket_file.display())
+            }
+            CommitBlockError::Daemon(message) => write!(f, "Commit Block couldn't handle the command: {}", message),
+            CommitBlockError::Hooks(message) => write!(f, "Failed to install git hooks: {}", message),
+            CommitBlockError::HostList(message) => write!(f, "Failed to read host list: {}", message),
+            CommitBlockError::InvalidHost(message) => write!(f, "{}", message),
+            CommitBlockError::KeyBindings(message) => write!(f, "Invalid key bindings in config file: {}", message),
+            CommitBlockError::NoHomeDirectory => {
+                write!(f, "Couldn't find a home directory to keep the config and state in; set HOME or pass --config and --state-dir")
+            }
+            CommitBlockError::MissingToken => write!(f, "No GitHub token found"),
+            CommitBlockError::Unauthorized => write!(f, "GitHub token is invalid or has expired (401)"),
+            CommitBlockError::RateLimited { reset_at: Some(reset_at) } => {
+                write!(f, "GitHub API rate limit exceeded until {}", reset_at.with_timezone(&Local).format("%H:%M"))
+            }
+            CommitBlockError::RateLimited { reset_at: None } => write!(f, "GitHub API rate limit exceeded"),
+            CommitBlockError::UserNotFound => write!(f, "GitHub user not found"),
+            CommitBlockError::Api(message) => write!(f, "GitHub API error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for CommitBlockError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CommitBlockError::Io(e) => Some(e),
+            CommitBlockError::Http(e) => Some(e),
+            CommitBlockError::Json(e) => Some(e),
+            CommitBlockError::ConfigParse(e) => Some(e),
+            CommitBlockError::ConfigSerialize(e) => Some(e),
+            CommitBlockError::StateParse(e) => Some(e),
+            CommitBlockError::HostsFile(e) => Some(e),
+            CommitBlockError::InsecureTokenFile(_)
+            | CommitBlockError::Secrets(_)
+            | CommitBlockError::NotRunning(_)
+            | CommitBlockError::AlreadyRunning(_)
+            | CommitBlockError::Daemon(_)
+            | CommitBlockError::Hooks(_)
+            | CommitBlockError::HostList(_)
+            | CommitBlockError::InvalidHost(_)
+            | CommitBlockError::KeyBindings(_)
+            | CommitBlockError::NoHomeDirectory
+            | CommitBlockError::MissingToken
+            | CommitBlockError::Unauthorized
+            | CommitBlockError::RateLimited { .. }
+            | CommitBlockError::UserNotFound
+            | CommitBlockError::Api(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for CommitBlockError {
+    fn from(e: io::Error) -> Self {
+        CommitBlockError::Io(e)
+    }
+}
+
+impl From<reqwest::Error> for CommitBlockError {
+    fn from(e: reqwest::Error) -> Self {
+        CommitBlockError::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for CommitBlockError {
+    fn from(e: serde_json::Error) -> Self {
+        CommitBlockError::Json(e)
+    }
+}
+
+impl From<toml::de::Error> for CommitBlockError {
+    fn from(e: toml::de::Error) -> Self {
+        CommitBlockError::ConfigParse(e)
+    }
+}
+
+impl From<toml::ser::Error> for CommitBlockError {
+    fn from(e: toml::ser::Error) -> Self {
+        CommitBlockError::ConfigSerialize(e)
+    }
+}
+
+impl From<chrono::ParseError> for CommitBlockError {
+    fn from(e: chrono::ParseError) -> Self {
+        CommitBlockError::StateParse(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_token_message() {
+        assert_eq!(CommitBlockError::MissingToken.to_string(), "No GitHub token found");
+    }
+
+    #[test]
+    fn io_error_converts() {
+        let error: CommitBlockError = io::Error::new(io::ErrorKind::PermissionDenied, "Permission denied").into();
+
+        assert!(matches!(error, CommitBlockError::Io(_)));
+        assert_eq!(error.to_string(), "Permission denied");
+    }
+
+    #[test]
+    fn api_errors_are_classified() {
+        assert!(CommitBlockError::Unauthorized.is_api_error());
+        assert!(CommitBlockError::UserNotFound.is_api_error());
+        assert!(!CommitBlockError::HostsFile(io::Error::other("read-only")).is_api_error());
+    }
+}
@@ -0,0 +1,646 @@
+use std::time::Duration;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+use reqwest::header::{self, HeaderMap};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::CommitBlockError;
+
+pub const GH_API_PATH: &str = "https://api.github.com/graphql";
+const GH_RATE_LIMIT_LIMIT_HEADER: &str = "x-ratelimit-limit";
+const GH_RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+const GH_RATE_LIMIT_RESET_HEADER: &str = "x-ratelimit-reset";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+const GH_OAUTH_SCOPES_HEADER: &str = "x-oauth-scopes";
+const GH_TOKEN_EXPIRATION_HEADER: &str = "github-authentication-token-expiration";
+const CONNECTION_QUERY: &str = r#"
+        query($userName:String!) {
+          viewer {
+            login
+          }
+          user(login: $userName) {
+            login
+          }
+        }
+       "#;
+const GRAPHQL_QUERY: &str = r#"
+        query($userName:String!, $from:DateTime!, $to:DateTime!) {
+          rateLimit {
+            limit
+            remaining
+            resetAt
+          }
+          user(login: $userName){
+            contributionsCollection(from: $from, to: $to) {
+              contributionCalendar {
+                totalContributions
+              }
+            }
+          }
+        }
+       "#;
+
+const CALENDAR_QUERY: &str = r#"
+        query($userName:String!, $from:DateTime!, $to:DateTime!) {
+          user(login: $userName){
+            contributionsCollection(from: $from, to: $to) {
+              contributionCalendar {
+                weeks {
+                  contributionDays {
+                    date
+                    contributionCount
+                  }
+                }
+              }
+            }
+          }
+        }
+       "#;
+
+struct RequestModel {
+    token: String,
+    variables: Value,
+}
+
+#[derive(Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlError {
+    #[serde(rename = "type")]
+    error_type: Option<String>,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContributionData {
+    rate_limit: Option<RateLimitData>,
+    user: Option<UserContributions>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RateLimitData {
+    limit: u32,
+    remaining: u32,
+    reset_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserContributions {
+    contributions_collection: ContributionsCollection,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContributionsCollection {
+    contribution_calendar: ContributionCalendar,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContributionCalendar {
+    total_contributions: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CalendarData {
+    user: Option<CalendarUser>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CalendarUser {
+    contributions_collection: CalendarCollection,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CalendarCollection {
+    contribution_calendar: CalendarWeeks,
+}
+
+#[derive(Deserialize)]
+struct CalendarWeeks {
+    weeks: Vec<CalendarWeek>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CalendarWeek {
+    contribution_days: Vec<CalendarDay>,
+}
+
+/// The number of contributions made on a single day
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    pub contribution_count: u32,
+}
+
+#[derive(Deserialize)]
+struct ConnectionData {
+    viewer: Login,
+    user: Option<Login>,
+}
+
+#[derive(Deserialize)]
+struct Login {
+    login: String,
+}
+
+/// The state of the GitHub API rate limit, as of the most recent request
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_at: DateTime<Utc>,
+}
+
+impl From<RateLimitData> for RateLimit {
+    fn from(rate_limit: RateLimitData) -> Self {
+        RateLimit {
+            limit: rate_limit.limit,
+            remaining: rate_limit.remaining,
+            reset_at: rate_limit.reset_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContributionProgress {
+    pub contribution_count: u32,
+    pub rate_limit: Option<RateLimit>,
+}
+
+/// The most recent response, which is reused if GitHub reports that it hasn't changed
+struct CachedResponse {
+    etag: String,
+    /// A different token may belong to a different account, which can see different contributions
+    token: String,
+    variables: Value,
+    progress: ContributionProgress,
+}
+
+/// The result of checking that the token works and the configured user exists
+#[derive(Debug, PartialEq)]
+pub struct ConnectionReport {
+    /// The user the token belongs to
+    pub viewer_login: String,
+    pub user_exists: bool,
+    /// Only classic personal access tokens report their scopes
+    pub scopes: Option<Vec<String>>,
+    /// Only tokens created with an expiry report it
+    pub token_expires_at: Option<DateTime<Utc>>,
+}
+
+impl ConnectionReport {
+    pub fn summary(&self, username: &str) -> String {
+        let user = if self.user_exists {
+            format!("user '{}' found", username)
+        } else {
+            format!("user '{}' not found", username)
+        };
+        let scopes = match &self.scopes {
+            Some(scopes) if scopes.is_empty() => "no scopes".to_string(),
+            Some(scopes) => format!("scopes: {}", scopes.join(", ")),
+            None => "scopes unknown".to_string(),
+        };
+        let expiry = match self.token_expires_at {
+            Some(expires_at) => format!("expires {}", expires_at.format("%Y-%m-%d")),
+            None => "no expiry".to_string(),
+        };
+        format!("Token belongs to {} ({}, {}), {}", self.viewer_login, scopes, expiry, user)
+    }
+}
+
+/// Checks that the token is valid and whether the user exists, without fetching any contributions
+pub async fn check_connection(client: &reqwest::Client, username: &String, token: &str) -> Result<ConnectionReport, CommitBlockError> {
+    let variables = serde_json::json!({
+        "userName": username
+    });
+    let response = post_graphql(client, token, CONNECTION_QUERY, &variables, None).await?;
+
+    let scopes = scopes_from_headers(response.headers());
+    let token_expires_at = token_expiry_from_headers(response.headers());
+
+    let response: GraphQlResponse<ConnectionData> = serde_json::from_str(&response.text().await?)?;
+    connection_report_from_response(response, scopes, token_expires_at)
+}
+
+/// Fetches the number of contributions made on each day between `from` and `to`, which can be at most a year apart.
+/// As with progress, the offset of `from` determines which timezone GitHub uses to group contributions into days.
+pub async fn fetch_contribution_calendar(client: &reqwest::Client, username: &String, token: &str, from: DateTime<FixedOffset>, to: DateTime<FixedOffset>) -> Result<Vec<CalendarDay>, CommitBlockError> {
+    let variables = serde_json::json!({
+        "userName": username,
+        "from": from.to_rfc3339(),
+        "to": to.to_rfc3339(),
+    });
+    let response = post_graphql(client, token, CALENDAR_QUERY, &variables, None).await?;
+
+    let reset_at = rate_limit_from_headers(response.headers()).map(|rate_limit| rate_limit.reset_at);
+    let response: GraphQlResponse<CalendarData> = serde_json::from_str(&response.text().await?)?;
+    calendar_from_response(response, reset_at)
+}
+
+fn calendar_from_response(response: GraphQlResponse<CalendarData>, reset_at: Option<DateTime<Utc>>) -> Result<Vec<CalendarDay>, CommitBlockError> {
+    check_graphql_errors(&response.errors, reset_at)?;
+    let user = response.data.ok_or_else(missing_data)?.user.ok_or(CommitBlockError::UserNotFound)?;
+
+    Ok(user.contributions_collection.contribution_calendar.weeks.into_iter()
+        .flat_map(|week| week.contribution_days)
+        .collect())
+}
+
+fn connection_report_from_response(response: GraphQlResponse<ConnectionData>, scopes: Option<Vec<String>>, token_expires_at: Option<DateTime<Utc>>) -> Result<ConnectionReport, CommitBlockError> {
+    // A missing user is reported as an error, but that's one of the things being checked for
+    if let Err(e) = check_graphql_errors(&response.errors, None) {
+        if !matches!(e, CommitBlockError::UserNotFound) {
+            return Err(e);
+        }
+    }
+    let data = response.data.ok_or_else(missing_data)?;
+
+    Ok(ConnectionReport {
+        viewer_login: data.viewer.login,
+        user_exists: data.user.is_some(),
+        scopes,
+        token_expires_at,
+    })
+}
+
+fn scopes_from_headers(headers: &HeaderMap) -> Option<Vec<String>> {
+    let scopes = headers.get(GH_OAUTH_SCOPES_HEADER)?.to_str().ok()?;
+    Some(scopes.split(',').map(str::trim).filter(|scope| !scope.is_empty()).map(String::from).collect())
+}
+
+/// The expiry is given as e.g. `2024-11-01 12:00:00 UTC`, or with a numeric offset in place of `UTC`
+fn token_expiry_from_headers(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    let expiry = headers.get(GH_TOKEN_EXPIRATION_HEADER)?.to_str().ok()?;
+
+    DateTime::parse_from_str(expiry, "%Y-%m-%d %H:%M:%S %z").map(|expiry| expiry.to_utc())
+        .or_else(|_| NaiveDateTime::parse_from_str(expiry.trim_end_matches(" UTC"), "%Y-%m-%d %H:%M:%S").map(|expiry| expiry.and_utc()))
+        .ok()
+}
+
+/// Fetches contribution progress, remembering the most recent response so that it can be reused when nothing has
+/// changed
+pub struct GitHubClient {
+    client: reqwest::Client,
+    last_response: Option<CachedResponse>,
+}
+
+impl GitHubClient {
+    pub fn new(client: reqwest::Client) -> GitHubClient {
+        GitHubClient {
+            client,
+            last_response: None,
+        }
+    }
+
+    /// Fetches the number of contributions made between `from` and `to`. The offset of `from` determines which
+    /// timezone GitHub uses to group contributions into days.
+    pub async fn check_contribution_progress(&mut self, username: &String, token: &str, from: DateTime<FixedOffset>, to: DateTime<FixedOffset>) -> Result<ContributionProgress, CommitBlockError> {
+        let request_info = build_request_model(username, token, from, to);
+
+        let cached = self.take_cached_response(&request_info.token, &request_info.variables);
+        let etag = cached.as_ref().map(|cached| cached.etag.as_str());
+        let response = post_graphql(&self.client, &request_info.token, GRAPHQL_QUERY, &request_info.variables, etag).await?;
+
+        let header_rate_limit = rate_limit_from_headers(response.headers());
+        let reset_at = header_rate_limit.as_ref().map(|rate_limit| rate_limit.reset_at);
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                return Ok(self.reuse_cached_response(cached, header_rate_limit));
+            }
+        }
+
+        let etag = response.headers().get(header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(String::from);
+        let response: GraphQlResponse<ContributionData> = serde_json::from_str(&response.text().await?)?;
+        check_graphql_errors(&response.errors, reset_at)?;
+        let data = response.data.ok_or_else(missing_data)?;
+
+        let progress = ContributionProgress {
+            contribution_count: find_contribution_count(&data)?,
+            rate_limit: header_rate_limit.or_else(|| data.rate_limit.map(RateLimit::from)),
+        };
+        if let Some(etag) = etag {
+            self.last_response = Some(CachedResponse {
+                etag,
+                token: request_info.token,
+                variables: request_info.variables,
+                progress: progress.clone(),
+            });
+        }
+        Ok(progress)
+    }
+
+    /// The cached response can only be reused for an identical request made with the same token, which won't be the
+    /// case once the day changes
+    fn take_cached_response(&mut self, token: &str, variables: &Value) -> Option<CachedResponse> {
+        self.last_response.take().filter(|cached| cached.token == token && cached.variables == *variables)
+    }
+
+    /// Answers a `304 Not Modified` response with the cached progress, which is kept for the next request
+    fn reuse_cached_response(&mut self, cached: CachedResponse, header_rate_limit: Option<RateLimit>) -> ContributionProgress {
+        let progress = ContributionProgress {
+            contribution_count: cached.progress.contribution_count,
+            rate_limit: header_rate_limit.or_else(|| cached.progress.rate_limit.clone()),
+        };
+        self.last_response = Some(cached);
+        progress
+    }
+}
+
+/// Sends a GraphQL query to GitHub. Responses saying that the token is invalid or the rate limit has been used up are
+/// turned into errors here, so every request handles them the same way. If `etag` is given, it's sent as
+/// `If-None-Match`, and a `304 Not Modified` response is returned for the caller to reuse what it has already.
+async fn post_graphql(client: &reqwest::Client, token: &str, query: &str, variables: &Value, etag: Option<&str>) -> Result<reqwest::Response, CommitBlockError> {
+    let mut request = client
+        .post(GH_API_PATH)
+        .header(header::USER_AGENT, "AppName/0.1")
+        .bearer_auth(token)
+        .json(&serde_json::json!({
+            "query": query,
+            "variables": variables,
+        }));
+    if let Some(etag) = etag {
+        request = request.header(header::IF_NONE_MATCH, etag);
+    }
+    let response = request.send().await?;
+
+    check_status(response.status(), rate_limit_from_headers(response.headers()))?;
+    Ok(response.error_for_status()?)
+}
+
+/// GitHub reports an exhausted rate limit with either `429 Too Many Requests`, or `403 Forbidden` with no requests
+/// remaining
+fn check_status(status: StatusCode, rate_limit: Option<RateLimit>) -> Result<(), CommitBlockError> {
+    let reset_at = rate_limit.as_ref().map(|rate_limit| rate_limit.reset_at);
+    match status {
+        StatusCode::UNAUTHORIZED => Err(CommitBlockError::Unauthorized),
+        StatusCode::TOO_MANY_REQUESTS => Err(CommitBlockError::RateLimited { reset_at }),
+        StatusCode::FORBIDDEN if rate_limit.is_some_and(|rate_limit| rate_limit.remaining == 0) => {
+            Err(CommitBlockError::RateLimited { reset_at })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Creates the HTTP client used for all requests to GitHub, which gives up on requests which take too long
+pub fn build_http_client() -> Result<reqwest::Client, CommitBlockError> {
+    Ok(reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()?)
+}
+
+fn build_request_model(username: &String, token: &str, from: DateTime<FixedOffset>, to: DateTime<FixedOffset>) -> RequestModel {
+    let variables = serde_json::json!({
+        "userName": username,
+        "from": from.to_rfc3339(),
+        "to": to.to_rfc3339(),
+    });
+
+    RequestModel {
+        token: token.to_string(),
+        variables,
+    }
+}
+
+/// The collection only covers the requested window, so its total is the number of contributions made in it
+fn find_contribution_count(data: &ContributionData) -> Result<u32, CommitBlockError> {
+    let user = data.user.as_ref().ok_or(CommitBlockError::UserNotFound)?;
+    Ok(user.contributions_collection.contribution_calendar.total_contributions)
+}
+
+fn missing_data() -> CommitBlockError {
+    CommitBlockError::Api("Response contained no data".to_string())
+}
+
+/// GraphQL requests can fail even when the response status is successful, in which case the reason is given in
+/// the `errors` field
+fn check_graphql_errors(errors: &[GraphQlError], reset_at: Option<DateTime<Utc>>) -> Result<(), CommitBlockError> {
+    let Some(error) = errors.first() else {
+        return Ok(());
+    };
+
+    match error.error_type.as_deref() {
+        Some("NOT_FOUND") => Err(CommitBlockError::UserNotFound),
+        Some("RATE_LIMITED") => Err(CommitBlockError::RateLimited { reset_at }),
+        _ if error.message.is_empty() => Err(CommitBlockError::Api("Unknown error".to_string())),
+        _ => Err(CommitBlockError::Api(error.message.clone())),
+    }
+}
+
+fn rate_limit_from_headers(headers: &HeaderMap) -> Option<RateLimit> {
+    let header_value = |name: &str| -> Option<i64> {
+        headers.get(name)?.to_str().ok()?.parse().ok()
+    };
+
+    Some(RateLimit {
+        limit: header_value(GH_RATE_LIMIT_LIMIT_HEADER)? as u32,
+        remaining: header_value(GH_RATE_LIMIT_REMAINING_HEADER)? as u32,
+        reset_at: DateTime::from_timestamp(header_value(GH_RATE_LIMIT_RESET_HEADER)?, 0)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gh_api_path() {
+        assert_eq!(GH_API_PATH, "https://api.github.com/graphql")
+    }
+
+    #[test]
+    fn can_build_request_model() {
+        let from = DateTime::parse_from_rfc3339("2024-10-27T04:00:00+01:00").unwrap();
+        let to = DateTime::parse_from_rfc3339("2024-10-28T04:00:00Z").unwrap();
+
+        let request_model = build_request_model(&String::from("BingBong"), "ghp_secret", from, to);
+
+        assert_eq!(request_model.variables["from"], "2024-10-27T04:00:00+01:00");
+        assert_eq!(request_model.variables["to"], "2024-10-28T04:00:00+00:00");
+    }
+
+    #[test]
+    fn can_find_contribution_count() {
+        let response: GraphQlResponse<ContributionData> = serde_json::from_str(r#"{"data":{"user":{"contributionsCollection":{"contributionCalendar":{"totalContributions":3}}}}}"#).unwrap();
+
+        assert_eq!(find_contribution_count(&response.data.unwrap()).unwrap(), 3);
+    }
+
+    #[test]
+    fn find_contribution_count_missing_user() {
+        let response: GraphQlResponse<ContributionData> = serde_json::from_str(r#"{"data":{"user":null}}"#).unwrap();
+
+        assert!(matches!(find_contribution_count(&response.data.unwrap()), Err(CommitBlockError::UserNotFound)));
+    }
+
+    fn cached_response(variables: &Value) -> CachedResponse {
+        CachedResponse {
+            etag: String::from("\"abc\""),
+            token: String::from("ghp_secret"),
+            variables: variables.clone(),
+            progress: ContributionProgress { contribution_count: 3, rate_limit: None },
+        }
+    }
+
+    #[test]
+    fn cached_response_only_reused_for_same_request() {
+        let from = DateTime::parse_from_rfc3339("2024-10-27T00:00:00Z").unwrap();
+        let to = DateTime::parse_from_rfc3339("2024-10-28T00:00:00Z").unwrap();
+        let variables = build_request_model(&String::from("BingBong"), "ghp_secret", from, to).variables;
+        let mut client = GitHubClient::new(reqwest::Client::new());
+        client.last_response = Some(cached_response(&variables));
+
+        let next_day = build_request_model(&String::from("BingBong"), "ghp_secret", to, to + chrono::TimeDelta::days(1)).variables;
+        assert!(client.take_cached_response("ghp_secret", &next_day).is_none());
+
+        client.last_response = Some(cached_response(&variables));
+        assert!(client.take_cached_response("ghp_other", &variables).is_none());
+
+        client.last_response = Some(cached_response(&variables));
+        assert_eq!(client.take_cached_response("ghp_secret", &variables).unwrap().etag, "\"abc\"");
+    }
+
+    #[test]
+    fn not_modified_response_reuses_cached_progress() {
+        let variables = serde_json::json!({ "userName": "BingBong" });
+        let mut client = GitHubClient::new(reqwest::Client::new());
+        let rate_limit = RateLimit { limit: 5000, remaining: 4321, reset_at: DateTime::from_timestamp(1730000000, 0).unwrap() };
+
+        let progress = client.reuse_cached_response(cached_response(&variables), Some(rate_limit.clone()));
+
+        assert_eq!(progress, ContributionProgress { contribution_count: 3, rate_limit: Some(rate_limit) });
+        // The cached response is kept, so the next request can be answered with a 304 too
+        assert!(client.take_cached_response("ghp_secret", &variables).is_some());
+    }
+
+    #[test]
+    fn check_graphql_errors_user_not_found() {
+        let response: GraphQlResponse<ContributionData> = serde_json::from_str(r#"{"data":{"user":null},"errors":[{"type":"NOT_FOUND","path":["user"],"message":"Could not resolve to a User with the login of 'BingBong'."}]}"#).unwrap();
+
+        let result = check_graphql_errors(&response.errors, None);
+
+        assert!(matches!(result, Err(CommitBlockError::UserNotFound)));
+    }
+
+    #[test]
+    fn check_graphql_errors_other_api_error() {
+        let response: GraphQlResponse<ContributionData> = serde_json::from_str(r#"{"errors":[{"message":"Something went wrong"}]}"#).unwrap();
+
+        let result = check_graphql_errors(&response.errors, None);
+
+        assert_eq!(result.unwrap_err().to_string(), "GitHub API error: Something went wrong");
+    }
+
+    #[test]
+    fn can_read_rate_limit_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(GH_RATE_LIMIT_LIMIT_HEADER, "5000".parse().unwrap());
+        headers.insert(GH_RATE_LIMIT_REMAINING_HEADER, "4321".parse().unwrap());
+        headers.insert(GH_RATE_LIMIT_RESET_HEADER, "1730000000".parse().unwrap());
+
+        let rate_limit = rate_limit_from_headers(&headers).unwrap();
+
+        assert_eq!(rate_limit.limit, 5000);
+        assert_eq!(rate_limit.remaining, 4321);
+        assert_eq!(rate_limit.reset_at, DateTime::from_timestamp(1730000000, 0).unwrap());
+    }
+
+    #[test]
+    fn can_read_rate_limit_from_response() {
+        let response: GraphQlResponse<ContributionData> = serde_json::from_str(r#"{"data":{"rateLimit":{"limit":5000,"remaining":12,"resetAt":"2024-10-27T03:33:20Z"},"user":null}}"#).unwrap();
+
+        let rate_limit = RateLimit::from(response.data.unwrap().rate_limit.unwrap());
+
+        assert_eq!(rate_limit.limit, 5000);
+        assert_eq!(rate_limit.remaining, 12);
+        assert_eq!(rate_limit.reset_at, DateTime::from_timestamp(1730000000, 0).unwrap());
+    }
+
+    #[test]
+    fn status_maps_to_errors() {
+        let exhausted = RateLimit { limit: 5000, remaining: 0, reset_at: DateTime::from_timestamp(1730000000, 0).unwrap() };
+        let remaining = RateLimit { remaining: 12, ..exhausted.clone() };
+
+        assert!(matches!(check_status(StatusCode::UNAUTHORIZED, None), Err(CommitBlockError::Unauthorized)));
+        assert!(matches!(check_status(StatusCode::TOO_MANY_REQUESTS, None), Err(CommitBlockError::RateLimited { reset_at: None })));
+        assert!(matches!(check_status(StatusCode::FORBIDDEN, Some(exhausted)), Err(CommitBlockError::RateLimited { reset_at: Some(_) })));
+        assert!(check_status(StatusCode::FORBIDDEN, Some(remaining)).is_ok());
+        assert!(check_status(StatusCode::NOT_MODIFIED, None).is_ok());
+    }
+
+    #[test]
+    fn missing_rate_limit_headers() {
+        assert_eq!(rate_limit_from_headers(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn can_read_token_details_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(GH_OAUTH_SCOPES_HEADER, "repo, read:user".parse().unwrap());
+        headers.insert(GH_TOKEN_EXPIRATION_HEADER, "2024-10-27 03:33:20 UTC".parse().unwrap());
+
+        assert_eq!(scopes_from_headers(&headers), Some(vec!(String::from("repo"), String::from("read:user"))));
+        assert_eq!(token_expiry_from_headers(&headers), DateTime::from_timestamp(1730000000, 0));
+    }
+
+    #[test]
+    fn can_read_token_expiry_with_offset() {
+        let mut headers = HeaderMap::new();
+        headers.insert(GH_TOKEN_EXPIRATION_HEADER, "2024-10-27 04:33:20 +0100".parse().unwrap());
+
+        assert_eq!(token_expiry_from_headers(&headers), DateTime::from_timestamp(1730000000, 0));
+    }
+
+    #[test]
+    fn connection_report_user_not_found() {
+        let response: GraphQlResponse<ConnectionData> = serde_json::from_str(r#"{"data":{"viewer":{"login":"BingBong"},"user":null},"errors":[{"type":"NOT_FOUND","path":["user"],"message":"Could not resolve to a User with the login of 'BongBing'."}]}"#).unwrap();
+
+        let report = connection_report_from_response(response, Some(vec!()), None).unwrap();
+
+        assert_eq!(report.viewer_login, "BingBong");
+        assert!(!report.user_exists);
+        assert_eq!(report.summary("BongBing"), "Token belongs to BingBong (no scopes, no expiry), user 'BongBing' not found");
+    }
+
+    #[test]
+    fn connection_report_user_found() {
+        let response: GraphQlResponse<ConnectionData> = serde_json::from_str(r#"{"data":{"viewer":{"login":"BingBong"},"user":{"login":"BingBong"}}}"#).unwrap();
+
+        let report = connection_report_from_response(response, None, None).unwrap();
+
+        assert!(report.user_exists);
+    }
+
+    #[test]
+    fn can_read_contribution_calendar() {
+        let response: GraphQlResponse<CalendarData> = serde_json::from_str(r#"{"data":{"user":{"contributionsCollection":{"contributionCalendar":{"weeks":[
+            {"contributionDays":[{"date":"2024-10-26","contributionCount":0}]},
+            {"contributionDays":[{"date":"2024-10-27","contributionCount":4},{"date":"2024-10-28","contributionCount":1}]}
+        ]}}}}}"#).unwrap();
+
+        let calendar = calendar_from_response(response, None).unwrap();
+
+        assert_eq!(calendar.len(), 3);
+        assert_eq!(calendar[1], CalendarDay { date: NaiveDate::from_ymd_opt(2024, 10, 27).unwrap(), contribution_count: 4 });
+    }
+}
@@ -0,0 +1,221 @@
+use crate::keys::{Action, KeyMap};
+
+/// The pages of the help screen, which opens on the one for the screen it was opened from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HelpSection {
+    Overview,
+    BlockedHosts,
+    Configuration,
+    BlockingBackends,
+    Troubleshooting,
+}
+
+/// A row of a section's table of keys. Keys bound to an action are looked up in the key map, so that the table shows
+/// the keys in use rather than the defaults
+enum KeyRow {
+    Bound(Action, &'static str),
+    /// Keys which always do the same thing and can't be changed
+    Fixed(&'static str, &'static str),
+}
+
+impl HelpSection {
+    pub const ALL: [HelpSection; 5] = [
+        HelpSection::Overview, HelpSection::BlockedHosts, HelpSection::Configuration, HelpSection::BlockingBackends,
+        HelpSection::Troubleshooting,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            HelpSection::Overview => "Overview",
+            HelpSection::BlockedHosts => "Blocked hosts",
+            HelpSection::Configuration => "Configuration",
+            HelpSection::BlockingBackends => "Blocking backends",
+            HelpSection::Troubleshooting => "Troubleshooting",
+        }
+    }
+
+    /// The section `offset` places after this one, wrapping around at either end
+    pub fn offset(self, offset: isize) -> HelpSection {
+        let index = HelpSection::ALL.iter().position(|section| *section == self).unwrap_or(0) as isize;
+        HelpSection::ALL[(index + offset).rem_euclid(HelpSection::ALL.len() as isize) as usize]
+    }
+
+    /// Paragraphs of text, which are wrapped to fit the screen
+    pub fn paragraphs(self) -> &'static [&'static str] {
+        match self {
+            HelpSection::Overview => &[
+                "Commit Block blocks a configured list of hosts until a given GitHub contribution goal has been met for the day. \
+                Once it's met, the hosts are unblocked until the start of the next day, when the goal needs to be met again.",
+                "Contribution progress is checked regularly (see `poll_interval_secs`), and less often once the goal has been met. The progress bar at the \
+                bottom of the screen shows how far there is to go. Commits reported by git hooks, installed with `commit-block hooks install`, \
+                are added to the progress straight away and marked as unconfirmed until GitHub has counted them, but the hosts are only \
+                unblocked once GitHub has counted enough contributions.",
+                "The history screen shows how many contributions were made on each day of the last year, with the days on which the goal \
+                was met marked with a diamond. Underneath is today's progress, one bar per half hour, along with how often the goal is met \
+                and the median time the hosts are unlocked on each weekday. Export it all with `commit-block history export`.",
+                "Every saved change to the hosts, bundles or configuration can be undone, and the hosts file and configuration file are \
+                updated straight away.",
+                "Hosts can be clicked to highlight them, fields to type into them and the hints at the bottom of the screen to act on \
+                them. Set `mouse = false` in the config file to select text in the terminal as usual instead.",
+            ],
+            HelpSection::BlockedHosts => &[
+                "The `Blocked hosts` panel lists the hosts which are blocked until today's contribution goal is met, followed by the \
+                enabled bundles. Its title shows how many hosts there are. Each host is marked with a closed lock if it's blocked in \
+                the hosts file, an open lock if it isn't, or an exclamation mark if only some of its entries are active or it's missing.",
+                "In Insert mode, type a host and save it to add it to the list. URLs can be pasted as they are, and are reduced to their \
+                lowercase hostname. Invalid hostnames and hosts which are already blocked are shown in red and aren't saved. Highlight an \
+                existing host to edit it in place or delete it, which then needs confirming. Cancelling throws away every change made \
+                since Insert mode was opened.",
+                "Long lists can be scrolled a line or a page at a time. Filter the list to only show the hosts containing what's typed, \
+                then keep the filter while editing or clear it to list every host again.",
+                "Bundles are built-in lists of distracting hosts, such as social media or news sites. Every host in an enabled bundle is \
+                blocked, and only the names of the enabled bundles are saved, so hosts added to a bundle in later versions are blocked \
+                too. Hosts can also be imported from a file with `commit-block hosts import`, and exported with `commit-block hosts export`.",
+            ],
+            HelpSection::Configuration => &[
+                "The `Configuration` panel shows the contribution goal, today's contribution count and the GitHub username. Open the \
+                configuration popup to change the goal or username, switching between the two fields as you go.",
+                "Test the connection to check that the GitHub token works and the username exists. A changed username is also checked when \
+                saving, and if it can't be found, it needs saving a second time to keep it anyway.",
+                "The GitHub token is read from `GITHUB_TOKEN`, the file named by `token_file` in the config file, the GitHub CLI's login, \
+                or the encrypted secrets file. Open the token screen to store a token in the secrets file along with a passphrase, or \
+                enter just the passphrase to unlock an existing one.",
+                "Other settings can be changed in `config.toml`, including how often progress is checked, what to do when GitHub can't be \
+                reached, the timezone and time at which the day starts, the theme, the keys and whether the mouse is used.",
+            ],
+            HelpSection::BlockingBackends => &[
+                "Hosts are blocked by pointing them at this machine in `/etc/hosts`, so Commit Block needs to be run with `sudo`. The \
+                hosts are written between the `### CommitBlock` and `### End CommitBlock` lines, each with one entry for IPv4 and one for \
+                IPv6. Anything else in the file is left alone.",
+                "When the goal is met, the entries are commented out rather than removed, and they're uncommented at the start of the next \
+                day. The hosts file is read every second, so that what's shown is what the file says, even after a failed write or \
+                an edit made by hand. The title bar shows ENFORCED when every host is blocked, RELAXED when none are, and INCONSISTENT \
+                otherwise, for example when only a host's IPv4 entry is active.",
+                "Pass `--hosts-file` to use a different file, for example when testing. With `--dry-run`, the hosts file is only read, and \
+                every change is made to a copy in memory instead. The most recent change is shown as a diff at the bottom of the screen.",
+            ],
+            HelpSection::Troubleshooting => &[
+                "A blocked site can still be reached: check that the host includes its top-level domain, such as `.com`, and that it \
+                matches the site being visited, including any `www.`. Browsers may also keep using an address they looked up before the \
+                host was blocked until they're restarted. The hosts are unblocked once the goal has been met, so check the progress too.",
+                "The contribution count is wrong: make sure the GitHub username is set. If it is, but progress is stuck at 0, test the \
+                connection from the configuration popup. The `Status` panel shows where the token was read from, when progress was last \
+                fetched and the last error from the GitHub API, such as an expired token or unknown username.",
+                "The hosts file can't be written: Commit Block needs to be run with `sudo`. Errors reading or writing the hosts file are \
+                shown in the `Status` panel.",
+                "Commit Block won't start after changing the keys: a key can only do one thing on each screen, and keys used while typing \
+                need Ctrl or Alt. The error explains which bindings conflict.",
+            ],
+        }
+    }
+
+    fn key_rows(self) -> &'static [KeyRow] {
+        match self {
+            HelpSection::Overview => &[
+                KeyRow::Fixed("F1", "Open help on the page for the current screen"),
+                KeyRow::Bound(Action::Help, "Open help from the main screen"),
+                KeyRow::Bound(Action::Refresh, "Check contribution progress straight away"),
+                KeyRow::Bound(Action::History, "Show the contribution history"),
+                KeyRow::Bound(Action::Undo, "Undo the last saved change"),
+                KeyRow::Bound(Action::Redo, "Redo the last undone change"),
+                KeyRow::Bound(Action::Quit, "Quit"),
+                KeyRow::Bound(Action::Confirm, "Confirm quitting"),
+            ],
+            HelpSection::BlockedHosts => &[
+                KeyRow::Bound(Action::Insert, "Open Insert mode"),
+                KeyRow::Bound(Action::Filter, "Filter the list of hosts"),
+                KeyRow::Fixed("Up/Down", "Highlight a host, or scroll the list on the main screen"),
+                KeyRow::Fixed("PageUp/PageDown", "Move a page at a time"),
+                KeyRow::Fixed("Left/Right/Home/End", "Move the cursor while typing a host"),
+                KeyRow::Bound(Action::EditHost, "Edit the highlighted host"),
+                KeyRow::Bound(Action::DeleteHost, "Delete the highlighted host"),
+                KeyRow::Bound(Action::Confirm, "Confirm deleting a host"),
+                KeyRow::Bound(Action::UndoUnsaved, "Undo the last change made in Insert mode"),
+                KeyRow::Bound(Action::Save, "Save the host, or keep the filter"),
+                KeyRow::Bound(Action::Cancel, "Leave Insert mode, or clear the filter"),
+                KeyRow::Bound(Action::Bundles, "Choose bundles"),
+                KeyRow::Fixed("Space", "Enable or disable the highlighted bundle"),
+            ],
+            HelpSection::Configuration => &[
+                KeyRow::Bound(Action::Configuration, "Edit the goal and username"),
+                KeyRow::Bound(Action::NextField, "Switch field"),
+                KeyRow::Bound(Action::TestConnection, "Test the GitHub token and username"),
+                KeyRow::Bound(Action::Save, "Save"),
+                KeyRow::Bound(Action::Cancel, "Close without saving"),
+                KeyRow::Bound(Action::Token, "Store or unlock the GitHub token"),
+            ],
+            HelpSection::BlockingBackends => &[],
+            HelpSection::Troubleshooting => &[
+                KeyRow::Bound(Action::TestConnection, "Test the GitHub token and username, from the configuration popup"),
+                KeyRow::Bound(Action::Refresh, "Check contribution progress straight away"),
+            ],
+        }
+    }
+
+    /// The keys used for this section's topic and what each does, using the keys from the key map
+    pub fn key_table(self, keys: &KeyMap) -> Vec<(String, &'static str)> {
+        self.key_rows().iter()
+            .map(|row| match row {
+                KeyRow::Bound(action, description) => (keys.chord(*action).to_string(), *description),
+                KeyRow::Fixed(key, description) => (key.to_string(), *description),
+            })
+            .collect()
+    }
+}
+
+/// Splits the text into lines no wider than `width`, breaking between words where possible
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        if !line.is_empty() && line.chars().count() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        // A word which doesn't fit on a line of its own is broken up
+        while word.len() > width {
+            lines.push(word.drain(..width).collect());
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.extend(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn key_table_shows_keys_in_use() {
+        let keys = KeyMap::new(&BTreeMap::from([(Action::Insert, "n".parse().unwrap())])).unwrap();
+
+        let table = HelpSection::BlockedHosts.key_table(&keys);
+
+        assert_eq!(table[0], (String::from("n"), "Open Insert mode"));
+        assert!(table.contains(&(String::from("Ctrl-e"), "Edit the highlighted host")));
+        assert!(table.contains(&(String::from("Space"), "Enable or disable the highlighted bundle")));
+    }
+
+    #[test]
+    fn sections_wrap_around() {
+        assert_eq!(HelpSection::Overview.offset(-1), HelpSection::Troubleshooting);
+        assert_eq!(HelpSection::Troubleshooting.offset(1), HelpSection::Overview);
+        assert_eq!(HelpSection::Overview.offset(2), HelpSection::Configuration);
+    }
+
+    #[test]
+    fn can_wrap_text() {
+        assert_eq!(wrap("one two three four", 9), vec!("one two", "three", "four"));
+        assert_eq!(wrap("abcdefghij kl", 4), vec!("abcd", "efgh", "ij", "kl"));
+        assert!(wrap("", 10).is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader};
+use std::path::Path;
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+
+use crate::day::ContributionDay;
+
+/// What has happened on previous days, kept so that it can be shown once the day is over
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct History {
+    /// Days on which the goal was met and the hosts were unlocked
+    #[serde(default)]
+    pub goal_met: BTreeMap<NaiveDate, GoalMet>,
+    /// Each change in the contribution count, by day
+    #[serde(default)]
+    pub progress: BTreeMap<NaiveDate, Vec<ProgressSample>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct GoalMet {
+    pub goal: u32,
+    pub unlocked_at: DateTime<Local>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ProgressSample {
+    pub at: DateTime<Local>,
+    pub contribution_count: u32,
+}
+
+/// When the goal tends to be met, over every day which has been recorded
+#[derive(Debug, PartialEq)]
+pub struct UnlockStats {
+    /// Days on which progress was recorded or the goal was met
+    pub tracked_days: usize,
+    pub goal_met_days: usize,
+    /// The median time at which the hosts were unlocked on each weekday, starting with Monday. The median is taken
+    /// over the time since the day started, so that unlocks after midnight count as the latest of the day.
+    pub median_unlock_times: [Option<NaiveTime>; 7],
+}
+
+impl UnlockStats {
+    pub fn goal_met_percent(&self) -> Option<f64> {
+        (self.tracked_days > 0).then(|| self.goal_met_days as f64 * 100.0 / self.tracked_days as f64)
+    }
+}
+
+impl History {
+    /// Records the goal as met on the given day. Only the first time is kept, as that's when the hosts were unlocked.
+    pub fn record_goal_met(&mut self, date: NaiveDate, goal: u32, unlocked_at: DateTime<Local>) {
+        self.goal_met.entry(date).or_insert(GoalMet { goal, unlocked_at });
+    }
+
+    /// Records the contribution count if it's changed since it was last recorded, returning whether it had
+    pub fn record_progress(&mut self, date: NaiveDate, at: DateTime<Local>, contribution_count: u32) -> bool {
+        let samples = self.progress.entry(date).or_default();
+        if samples.last().is_some_and(|sample| sample.contribution_count == contribution_count) {
+            return false;
+        }
+        samples.push(ProgressSample { at, contribution_count });
+        true
+    }
+
+    /// The contribution count at the end of each interval from the start of the day until now
+    pub fn progress_timeline(&self, date: NaiveDate, start: DateTime<Local>, interval: TimeDelta, now: DateTime<Local>) -> Vec<u64> {
+        let samples = self.progress.get(&date).map(Vec::as_slice).unwrap_or_default();
+        let mut timeline = Vec::new();
+        let mut end = start + interval;
+        while end - interval < now {
+            let count = samples.iter()
+                .take_while(|sample| sample.at < end)
+                .last()
+                .map_or(0, |sample| sample.contribution_count);
+            timeline.push(u64::from(count));
+            end += interval;
+        }
+        timeline
+    }
+
+    pub fn unlock_stats(&self, contribution_day: &ContributionDay) -> UnlockStats {
+        let mut unlock_times: [Vec<i64>; 7] = Default::default();
+        for (date, goal_met) in &self.goal_met {
+            // Measured on the wall clock, so that the clocks changing doesn't move the time shown
+            let (start, _) = contribution_day.bounds(*date);
+            let unlocked_at = contribution_day.local_time(goal_met.unlocked_at.to_utc());
+            let since_start = (unlocked_at - start.naive_local()).num_seconds().max(0);
+            unlock_times[date.weekday().num_days_from_monday() as usize].push(since_start);
+        }
+
+        UnlockStats {
+            tracked_days: self.progress.keys().chain(self.goal_met.keys()).collect::<BTreeSet<_>>().len(),
+            goal_met_days: self.goal_met.len(),
+            median_unlock_times: unlock_times.map(|mut seconds| {
+                seconds.sort_unstable();
+                let median = match seconds.len() {
+                    0 => return None,
+                    len if len % 2 == 0 => (seconds[len / 2 - 1] + seconds[len / 2]) / 2,
+                    len => seconds[len / 2],
+                };
+                Some(contribution_day.day_start().overflowing_add_signed(TimeDelta::seconds(median)).0)
+            }),
+        }
+    }
+
+    /// Every recorded change in progress and every unlock, one per row, ordered by when they happened
+    pub fn to_csv(&self) -> String {
+        let mut rows: Vec<(DateTime<Local>, String)> = Vec::new();
+        for (date, samples) in &self.progress {
+            for sample in samples {
+                rows.push((sample.at, format!("{},progress,{},{},", date, sample.at.to_rfc3339(), sample.contribution_count)));
+            }
+        }
+        for (date, goal_met) in &self.goal_met {
+            rows.push((goal_met.unlocked_at, format!("{},unlocked,{},,{}", date, goal_met.unlocked_at.to_rfc3339(), goal_met.goal)));
+        }
+        rows.sort_by_key(|(at, _)| *at);
+
+        let mut csv = String::from("date,event,time,contribution_count,goal\n");
+        for (_, row) in rows {
+            csv.push_str(&row);
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Loads the history, starting a new one if the file doesn't exist or can't be read
+pub fn load_history(file_path: impl AsRef<Path>) -> History {
+    File::open(file_path).ok()
+        .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+        .unwrap_or_default()
+}
+
+pub fn persist_history(file_path: impl AsRef<Path>, history: &History) -> io::Result<()> {
+    let file = OpenOptions::new().write(true).create(true).truncate(true).open(file_path)?;
+    serde_json::to_writer_pretty(file, history).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn goal_met_is_only_recorded_once_per_day() {
+        let mut history = History::default();
+        let date = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+        let unlocked_at = Local::now();
+
+        history.record_goal_met(date, 3, unlocked_at);
+        history.record_goal_met(date, 5, unlocked_at + TimeDelta::hours(1));
+
+        assert_eq!(history.goal_met[&date], GoalMet { goal: 3, unlocked_at });
+    }
+
+    #[test]
+    fn history_can_be_persisted() {
+        let file_path = env::temp_dir().join(format!("commit-block-history-{}.json", std::process::id()));
+        assert_eq!(load_history(&file_path), History::default());
+
+        let mut history = History::default();
+        history.record_goal_met(NaiveDate::from_ymd_opt(2024, 10, 27).unwrap(), 3, Local::now());
+        persist_history(&file_path, &history).unwrap();
+
+        assert_eq!(load_history(&file_path), history);
+        fs::remove_file(file_path).unwrap();
+    }
+
+    fn at(date: NaiveDate, hour: u32, minute: u32) -> DateTime<Local> {
+        date.and_hms_opt(hour, minute, 0).unwrap().and_local_timezone(Local).earliest().unwrap()
+    }
+
+    #[test]
+    fn progress_is_only_recorded_when_it_changes() {
+        let mut history = History::default();
+        let date = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+
+        assert!(history.record_progress(date, at(date, 9, 0), 0));
+        assert!(!history.record_progress(date, at(date, 9, 5), 0));
+        assert!(history.record_progress(date, at(date, 10, 0), 1));
+
+        assert_eq!(history.progress[&date].len(), 2);
+    }
+
+    #[test]
+    fn can_build_progress_timeline() {
+        let mut history = History::default();
+        let date = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+        history.record_progress(date, at(date, 1, 30), 1);
+        history.record_progress(date, at(date, 3, 0), 3);
+
+        let timeline = history.progress_timeline(date, at(date, 0, 0), TimeDelta::hours(1), at(date, 3, 30));
+
+        assert_eq!(timeline, vec!(0, 1, 1, 3));
+    }
+
+    #[test]
+    fn can_calculate_unlock_stats() {
+        let mut history = History::default();
+        // Both of these were Sundays
+        let first = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+        let second = NaiveDate::from_ymd_opt(2024, 11, 3).unwrap();
+        let missed = NaiveDate::from_ymd_opt(2024, 10, 28).unwrap();
+        history.record_goal_met(first, 1, at(first, 10, 0));
+        history.record_goal_met(second, 1, at(second, 14, 0));
+        history.record_progress(missed, at(missed, 9, 0), 0);
+
+        let stats = history.unlock_stats(&ContributionDay::new(None, None));
+
+        assert_eq!(stats.tracked_days, 3);
+        assert_eq!(stats.goal_met_days, 2);
+        assert_eq!(stats.median_unlock_times[6], NaiveTime::from_hms_opt(12, 0, 0));
+        assert_eq!(stats.median_unlock_times[0], None);
+        assert!((stats.goal_met_percent().unwrap() - 66.7).abs() < 0.1);
+    }
+
+    #[test]
+    fn unlock_stats_measured_from_day_start() {
+        let mut history = History::default();
+        let contribution_day = ContributionDay::new(None, NaiveTime::from_hms_opt(4, 0, 0));
+        // Both of these were Sundays, and the first was unlocked at 02:00 on the Monday, before the day changed
+        let first = NaiveDate::from_ymd_opt(2024, 6, 2).unwrap();
+        let second = NaiveDate::from_ymd_opt(2024, 6, 9).unwrap();
+        history.record_goal_met(first, 1, at(first + TimeDelta::days(1), 2, 0));
+        history.record_goal_met(second, 1, at(second, 23, 0));
+
+        let stats = history.unlock_stats(&contribution_day);
+
+        assert_eq!(stats.median_unlock_times[6], NaiveTime::from_hms_opt(0, 30, 0));
+        assert_eq!(stats.median_unlock_times[0], None);
+    }
+
+    #[test]
+    fn can_export_csv() {
+        let mut history = History::default();
+        let date = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+        history.record_progress(date, at(date, 9, 0), 1);
+        history.record_goal_met(date, 1, at(date, 9, 0) + TimeDelta::seconds(1));
+
+        let csv = history.to_csv();
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "date,event,time,contribution_count,goal");
+        assert_eq!(lines[1], format!("2024-10-27,progress,{},1,", at(date, 9, 0).to_rfc3339()));
+        assert!(lines[2].starts_with("2024-10-27,unlocked,") && lines[2].ends_with(",,1"));
+    }
+}
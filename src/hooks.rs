@@ -0,0 +1,130 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::CommitBlockError;
+
+/// Identifies hooks written by Commit Block, which can safely be replaced when reinstalling
+const HOOK_MARKER: &str = "# Installed by commit-block";
+const BACKUP_SUFFIX: &str = ".commit-block.bak";
+/// The hooks which are installed, along with the event each one reports
+const HOOKS: [(&str, &str); 2] = [("post-commit", "commit"), ("pre-push", "push")];
+
+/// Installs hooks into the repository which let the running application know about new commits and pushes. Hooks
+/// which weren't installed by Commit Block are only replaced if forced, in which case they're backed up first.
+pub fn install_hooks(repo: &Path, executable: &Path, state_dir: &Path, force: bool) -> Result<Vec<PathBuf>, CommitBlockError> {
+    let hooks_dir = find_hooks_dir(repo)?;
+    fs::create_dir_all(&hooks_dir)?;
+
+    HOOKS.iter()
+        .map(|(name, event)| write_hook(&hooks_dir.join(name), &hook_script(executable, state_dir, event), force))
+        .collect()
+}
+
+/// Asks git where the hooks live, which takes `core.hooksPath` and worktrees into account
+fn find_hooks_dir(repo: &Path) -> Result<PathBuf, CommitBlockError> {
+    let output = Command::new("git")
+        .arg("-C").arg(repo)
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()
+        .map_err(|e| CommitBlockError::Hooks(format!("couldn't run git: {}", e)))?;
+    if !output.status.success() {
+        return Err(CommitBlockError::Hooks(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+
+    let hooks_dir = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    Ok(if hooks_dir.is_absolute() { hooks_dir } else { repo.join(hooks_dir) })
+}
+
+/// The hooks never fail, so that commits and pushes still work when Commit Block isn't running
+fn hook_script(executable: &Path, state_dir: &Path, event: &str) -> String {
+    format!(
+        "#!/bin/sh\n{}\n{} --state-dir {} notify {} >/dev/null 2>&1\nexit 0\n",
+        HOOK_MARKER,
+        shell_quote(&executable.to_string_lossy()),
+        shell_quote(&state_dir.to_string_lossy()),
+        event,
+    )
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn write_hook(hook_file: &Path, script: &str, force: bool) -> Result<PathBuf, CommitBlockError> {
+    if let Ok(existing) = fs::read_to_string(hook_file) {
+        if !existing.contains(HOOK_MARKER) {
+            if !force {
+                return Err(CommitBlockError::Hooks(format!("{} already exists, use --force to replace it", hook_file.display())));
+            }
+            let mut backup = hook_file.as_os_str().to_owned();
+            backup.push(BACKUP_SUFFIX);
+            fs::rename(hook_file, backup)?;
+        }
+    }
+
+    fs::write(hook_file, script)?;
+    fs::set_permissions(hook_file, fs::Permissions::from_mode(0o755))?;
+    Ok(hook_file.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("commit-block-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn can_build_hook_script() {
+        let script = hook_script(Path::new("/usr/bin/commit-block"), Path::new("/home/o'brien/state"), "commit");
+
+        assert_eq!(script, "#!/bin/sh\n# Installed by commit-block\n'/usr/bin/commit-block' --state-dir '/home/o'\\''brien/state' notify commit >/dev/null 2>&1\nexit 0\n");
+    }
+
+    #[test]
+    fn write_hook_refuses_existing_hook() {
+        let dir = temp_dir("hooks-existing");
+        let hook_file = dir.join("post-commit");
+        fs::write(&hook_file, "#!/bin/sh\nmake lint\n").unwrap();
+
+        let result = write_hook(&hook_file, "#!/bin/sh\n", false);
+
+        assert!(matches!(result, Err(CommitBlockError::Hooks(_))));
+        assert_eq!(fs::read_to_string(&hook_file).unwrap(), "#!/bin/sh\nmake lint\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn write_hook_force_backs_up_existing_hook() {
+        let dir = temp_dir("hooks-force");
+        let hook_file = dir.join("post-commit");
+        fs::write(&hook_file, "#!/bin/sh\nmake lint\n").unwrap();
+
+        write_hook(&hook_file, &format!("{}\n", HOOK_MARKER), true).unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join("post-commit.commit-block.bak")).unwrap(), "#!/bin/sh\nmake lint\n");
+        assert_eq!(fs::metadata(&hook_file).unwrap().permissions().mode() & 0o777, 0o755);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn write_hook_replaces_own_hook() {
+        let dir = temp_dir("hooks-reinstall");
+        let hook_file = dir.join("pre-push");
+        fs::write(&hook_file, format!("#!/bin/sh\n{}\nold\n", HOOK_MARKER)).unwrap();
+
+        write_hook(&hook_file, &format!("#!/bin/sh\n{}\nnew\n", HOOK_MARKER), false).unwrap();
+
+        assert!(fs::read_to_string(&hook_file).unwrap().ends_with("new\n"));
+        assert!(!dir.join("pre-push.commit-block.bak").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+use std::net::IpAddr;
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::error::CommitBlockError;
+use crate::hostname::remove_duplicates;
+
+/// Hosts which are part of a standard hosts file, rather than ones which have been blocked
+const STANDARD_HOSTS: [&str; 4] = ["localhost", "localhost.localdomain", "broadcasthost", "local"];
+
+/// The formats which lists of hosts can be imported from and exported to
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum HostListFormat {
+    /// Work out the format from the file name and contents. Only used when importing
+    Auto,
+    /// One host per line, with `#` comments
+    Text,
+    /// The format of `/etc/hosts`, with an address followed by one or more hosts on each line
+    Hosts,
+    /// An array of hosts, or an object with a `hosts` array
+    Json,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonHostList {
+    Hosts(Vec<String>),
+    Object { hosts: Vec<String> },
+}
+
+impl HostListFormat {
+    fn detect(path: &Path, content: &str) -> HostListFormat {
+        if path.extension().is_some_and(|extension| extension == "json") || content.trim_start().starts_with(['[', '{']) {
+            HostListFormat::Json
+        } else if content_lines(content).any(starts_with_address) {
+            HostListFormat::Hosts
+        } else {
+            HostListFormat::Text
+        }
+    }
+}
+
+/// Reads the hosts from the contents of a file, in the order they appear
+pub fn parse_host_list(path: &Path, content: &str, format: HostListFormat) -> Result<Vec<String>, CommitBlockError> {
+    let hosts: Vec<String> = match format {
+        HostListFormat::Auto => return parse_host_list(path, content, HostListFormat::detect(path, content)),
+        HostListFormat::Text => content_lines(content)
+            .map(String::from)
+            .collect(),
+        HostListFormat::Hosts => content_lines(content)
+            .filter(|line| starts_with_address(line))
+            .flat_map(|line| line.split_whitespace().skip(1))
+            .filter(|host| !STANDARD_HOSTS.contains(host) && !host.starts_with("ip6-"))
+            .map(String::from)
+            .collect(),
+        HostListFormat::Json => match serde_json::from_str(content).map_err(|e| CommitBlockError::HostList(e.to_string()))? {
+            JsonHostList::Hosts(hosts) | JsonHostList::Object { hosts } => hosts,
+        },
+    };
+
+    Ok(remove_duplicates(hosts))
+}
+
+pub fn format_host_list(hosts: &[String], format: HostListFormat) -> String {
+    match format {
+        HostListFormat::Json => serde_json::json!({ "hosts": hosts }).to_string() + "\n",
+        HostListFormat::Hosts => hosts.iter().map(|host| format!("127.0.0.1\t{}\n", host)).collect(),
+        HostListFormat::Auto | HostListFormat::Text => hosts.iter().map(|host| format!("{}\n", host)).collect(),
+    }
+}
+
+/// The non-empty lines of a file, without comments
+fn content_lines(content: &str) -> impl Iterator<Item = &str> {
+    content.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+}
+
+fn starts_with_address(line: &str) -> bool {
+    line.split_whitespace().next().is_some_and(|address| address.parse::<IpAddr>().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_text_list() {
+        let hosts = parse_host_list(Path::new("hosts.txt"), "# Distractions\nexample.com\n\nexample.org # news\nexample.com\n", HostListFormat::Auto).unwrap();
+
+        assert_eq!(hosts, vec!(String::from("example.com"), String::from("example.org")));
+    }
+
+    #[test]
+    fn can_parse_hosts_file() {
+        let content = "127.0.0.1\tlocalhost\n::1\tlocalhost ip6-localhost\n0.0.0.0 example.com www.example.com\n#0.0.0.0 example.org\n";
+
+        let hosts = parse_host_list(Path::new("hosts"), content, HostListFormat::Auto).unwrap();
+
+        assert_eq!(hosts, vec!(String::from("example.com"), String::from("www.example.com")));
+    }
+
+    #[test]
+    fn can_parse_json_list() {
+        let array = parse_host_list(Path::new("hosts"), r#"["example.com"]"#, HostListFormat::Auto).unwrap();
+        let object = parse_host_list(Path::new("hosts.json"), r#"{"hosts":["example.com"]}"#, HostListFormat::Auto).unwrap();
+
+        assert_eq!(array, vec!(String::from("example.com")));
+        assert_eq!(object, array);
+    }
+
+    #[test]
+    fn parse_invalid_json_list() {
+        let result = parse_host_list(Path::new("hosts.json"), r#"{"domains":["example.com"]}"#, HostListFormat::Auto);
+
+        assert!(matches!(result, Err(CommitBlockError::HostList(_))));
+    }
+
+    #[test]
+    fn formatted_lists_can_be_parsed() {
+        let hosts = vec!(String::from("example.com"), String::from("example.org"));
+
+        for format in [HostListFormat::Text, HostListFormat::Hosts, HostListFormat::Json] {
+            let content = format_host_list(&hosts, format);
+            assert_eq!(parse_host_list(Path::new("export"), &content, format).unwrap(), hosts);
+        }
+    }
+}
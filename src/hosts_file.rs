@@ -0,0 +1,131 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use similar::TextDiff;
+
+/// Handle to the hosts file which Commit Block reads from and writes to.
+///
+/// In dry-run mode, the real file is only ever read once to seed an in-memory copy. All writes are
+/// applied to that copy instead, and a unified diff of the most recent change is kept for display.
+pub struct HostsFile {
+    path: PathBuf,
+    dry_run: Option<Mutex<DryRunState>>,
+}
+
+struct DryRunState {
+    content: String,
+    last_diff: Option<String>,
+}
+
+impl HostsFile {
+    pub fn new(path: impl Into<PathBuf>) -> HostsFile {
+        HostsFile {
+            path: path.into(),
+            dry_run: None,
+        }
+    }
+
+    pub fn dry_run(path: impl Into<PathBuf>) -> HostsFile {
+        let path = path.into();
+        // The real file may not be readable without elevated privileges, in which case start from an empty file
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        HostsFile {
+            path,
+            dry_run: Some(Mutex::new(DryRunState {
+                content,
+                last_diff: None,
+            })),
+        }
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run.is_some()
+    }
+
+    pub fn read(&self) -> io::Result<String> {
+        match &self.dry_run {
+            Some(state) => Ok(state.lock().unwrap().content.clone()),
+            None => fs::read_to_string(&self.path),
+        }
+    }
+
+    pub fn write(&self, content: &str) -> io::Result<()> {
+        match &self.dry_run {
+            Some(state) => {
+                let mut state = state.lock().unwrap();
+                if state.content != content {
+                    state.last_diff = Some(unified_diff(&self.path, &state.content, content));
+                    state.content = content.to_string();
+                }
+                Ok(())
+            }
+            None => {
+                let mut file = File::create(&self.path)?;
+                file.write_all(content.as_bytes())
+            }
+        }
+    }
+
+    /// The diff produced by the most recent dry-run write which changed the file, if any
+    pub fn last_diff(&self) -> Option<String> {
+        self.dry_run.as_ref()
+            .and_then(|state| state.lock().unwrap().last_diff.clone())
+    }
+}
+
+fn unified_diff(path: &Path, old: &str, new: &str) -> String {
+    let path = path.display().to_string();
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a{}", path), &format!("b{}", path))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_run_write_does_not_touch_file() {
+        let hosts_file = HostsFile::dry_run("doesNotExist");
+
+        hosts_file.write("127.0.0.1\tlocalhost\n").unwrap();
+
+        assert!(!Path::new("doesNotExist").exists());
+        assert_eq!(hosts_file.read().unwrap(), "127.0.0.1\tlocalhost\n");
+    }
+
+    #[test]
+    fn dry_run_write_records_diff() {
+        let hosts_file = HostsFile::dry_run("doesNotExist");
+        hosts_file.write("a\nb\n").unwrap();
+
+        hosts_file.write("a\n#b\n").unwrap();
+
+        let diff = hosts_file.last_diff().unwrap();
+        assert!(diff.contains("-b\n"));
+        assert!(diff.contains("+#b\n"));
+    }
+
+    #[test]
+    fn dry_run_unchanged_write_keeps_previous_diff() {
+        let hosts_file = HostsFile::dry_run("doesNotExist");
+        hosts_file.write("a\n").unwrap();
+        let diff = hosts_file.last_diff();
+
+        hosts_file.write("a\n").unwrap();
+
+        assert_eq!(hosts_file.last_diff(), diff);
+    }
+
+    #[test]
+    fn real_file_has_no_diff() {
+        let hosts_file = HostsFile::new("doesNotExist");
+
+        assert!(!hosts_file.is_dry_run());
+        assert_eq!(hosts_file.last_diff(), None);
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::error::CommitBlockError;
+
+/// Something which can be done by pressing a key, and which can be bound to a different key in the `[keys]` table of the
+/// config file
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Quit,
+    Insert,
+    Help,
+    Configuration,
+    Token,
+    Refresh,
+    Bundles,
+    History,
+    /// Undoes the last saved change, from the main screen
+    Undo,
+    Redo,
+    /// Undoes the last change made on the editing screen, which hasn't been saved yet
+    UndoUnsaved,
+    EditHost,
+    DeleteHost,
+    TestConnection,
+    NextField,
+    Save,
+    Cancel,
+    /// Answers yes when asked to quit or delete a host
+    Confirm,
+    /// Starts typing a filter for the list of hosts
+    Filter,
+}
+
+/// Each screen, or group of screens, on which a key can only do one thing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scope {
+    Main,
+    Editing,
+    Configuration,
+    Token,
+    Confirming,
+    Bundles,
+    History,
+    /// Typing a filter for the list of hosts
+    Filter,
+    Help,
+}
+
+const SCOPES: [Scope; 9] = [
+    Scope::Main, Scope::Editing, Scope::Configuration, Scope::Token, Scope::Confirming, Scope::Bundles, Scope::History, Scope::Filter,
+    Scope::Help,
+];
+
+/// Opens help on the page for the current screen, from any screen including those where characters are typed
+pub const HELP_KEY: KeyCode = KeyCode::F(1);
+
+impl Action {
+    const ALL: [Action; 19] = [
+        Action::Quit, Action::Insert, Action::Help, Action::Configuration, Action::Token, Action::Refresh, Action::Bundles,
+        Action::History, Action::Undo, Action::Redo, Action::UndoUnsaved, Action::EditHost, Action::DeleteHost,
+        Action::TestConnection, Action::NextField, Action::Save, Action::Cancel, Action::Confirm, Action::Filter,
+    ];
+
+    fn default_chord(self) -> KeyChord {
+        match self {
+            Action::Quit => KeyChord::char('q'),
+            Action::Insert => KeyChord::char('i'),
+            Action::Help => KeyChord::char('h'),
+            Action::Configuration => KeyChord::char('c'),
+            Action::Token => KeyChord::char('t'),
+            Action::Refresh => KeyChord::char('r'),
+            Action::Bundles => KeyChord::char('b'),
+            Action::History => KeyChord::char('a'),
+            Action::Undo => KeyChord::char('u'),
+            Action::Redo => KeyChord::ctrl('r'),
+            Action::UndoUnsaved => KeyChord::ctrl('u'),
+            Action::EditHost => KeyChord::ctrl('e'),
+            Action::DeleteHost => KeyChord::ctrl('d'),
+            Action::TestConnection => KeyChord::ctrl('t'),
+            Action::NextField => KeyChord::new(KeyCode::Tab, KeyModifiers::NONE),
+            Action::Save => KeyChord::new(KeyCode::Enter, KeyModifiers::NONE),
+            Action::Cancel => KeyChord::new(KeyCode::Esc, KeyModifiers::NONE),
+            Action::Confirm => KeyChord::char('y'),
+            Action::Filter => KeyChord::char('/'),
+        }
+    }
+
+    fn scopes(self) -> &'static [Scope] {
+        match self {
+            Action::Quit => &[Scope::Main, Scope::History],
+            Action::Insert | Action::Configuration | Action::Token | Action::Refresh | Action::Undo => &[Scope::Main],
+            Action::Help => &[Scope::Main, Scope::Bundles, Scope::History, Scope::Help],
+            Action::Bundles => &[Scope::Main, Scope::Bundles],
+            Action::History => &[Scope::Main, Scope::History],
+            Action::Redo => &[Scope::Main, Scope::Editing],
+            Action::UndoUnsaved | Action::EditHost | Action::DeleteHost => &[Scope::Editing],
+            Action::TestConnection => &[Scope::Configuration],
+            Action::NextField => &[Scope::Configuration, Scope::Token, Scope::Help],
+            Action::Save => &[Scope::Editing, Scope::Configuration, Scope::Token, Scope::Filter],
+            Action::Cancel => &[
+                Scope::Editing, Scope::Configuration, Scope::Token, Scope::Confirming, Scope::Bundles, Scope::History, Scope::Filter,
+                Scope::Help,
+            ],
+            Action::Confirm => &[Scope::Confirming],
+            Action::Filter => &[Scope::Main],
+        }
+    }
+
+    fn name(self) -> String {
+        toml::Value::try_from(self).ok()
+            .and_then(|value| value.as_str().map(String::from))
+            .unwrap_or_default()
+    }
+}
+
+impl Scope {
+    /// Whether characters are typed into a field on this screen, so can't be bound without Ctrl or Alt
+    fn takes_text(self) -> bool {
+        matches!(self, Scope::Editing | Scope::Configuration | Scope::Token | Scope::Filter)
+    }
+
+    /// Keys which always do the same thing on this screen, and can't be bound to anything else
+    fn reserved_keys(self) -> Vec<KeyChord> {
+        let codes: &[KeyCode] = match self {
+            Scope::Main => &[KeyCode::Up, KeyCode::Down, KeyCode::PageUp, KeyCode::PageDown],
+            Scope::Editing => &[
+                KeyCode::Up, KeyCode::Down, KeyCode::PageUp, KeyCode::PageDown, KeyCode::Left, KeyCode::Right, KeyCode::Home,
+                KeyCode::End, KeyCode::Backspace, KeyCode::Delete,
+            ],
+            Scope::Filter => &[KeyCode::Up, KeyCode::Down, KeyCode::PageUp, KeyCode::PageDown, KeyCode::Backspace],
+            Scope::Configuration | Scope::Token => &[KeyCode::Backspace],
+            Scope::Bundles => &[KeyCode::Up, KeyCode::Down, KeyCode::Char(' '), KeyCode::Enter],
+            Scope::Confirming | Scope::History => &[],
+            Scope::Help => &[
+                KeyCode::Up, KeyCode::Down, KeyCode::PageUp, KeyCode::PageDown, KeyCode::Home, KeyCode::End, KeyCode::Left,
+                KeyCode::Right, KeyCode::BackTab,
+            ],
+        };
+        let help = (self != Scope::Confirming).then_some(HELP_KEY);
+        codes.iter().copied().chain(help).map(|code| KeyChord::new(code, KeyModifiers::NONE)).collect()
+    }
+}
+
+/// A key along with any modifiers which need to be held down, written like `q`, `ctrl-r` or `enter` in the config file
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+        // Shift is already part of the character typed, so is only kept for other keys
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        KeyChord { code, modifiers }
+    }
+
+    fn char(c: char) -> KeyChord {
+        KeyChord::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyChord {
+        KeyChord::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    /// The key press which would trigger this chord, used to act on clicks in the same way as keys
+    pub fn key_event(&self) -> KeyEvent {
+        KeyEvent::new(self.code, self.modifiers)
+    }
+
+    fn is_typed(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_)) && !self.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+}
+
+impl TryFrom<String> for KeyChord {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl std::str::FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = value.trim();
+        // A separator on its own is the key itself, e.g. `ctrl--`
+        while let Some((prefix, rest)) = key.split_once(['-', '+']).filter(|(prefix, rest)| !prefix.is_empty() && !rest.is_empty()) {
+            modifiers |= match prefix.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("'{}' isn't a modifier in '{}', use ctrl-, alt- or shift-", prefix, value)),
+            };
+            key = rest;
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|number| number.parse().ok()) {
+                    Some(number @ 1..=12) => KeyCode::F(number),
+                    _ => return Err(format!("'{}' isn't a key", value)),
+                },
+            },
+        };
+        Ok(KeyChord::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [(KeyModifiers::CONTROL, "Ctrl-"), (KeyModifiers::ALT, "Alt-"), (KeyModifiers::SHIFT, "Shift-")] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(number) => write!(f, "F{}", number),
+            KeyCode::BackTab => f.write_str("BackTab"),
+            KeyCode::PageUp => f.write_str("PageUp"),
+            KeyCode::PageDown => f.write_str("PageDown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+impl From<KeyChord> for String {
+    fn from(chord: KeyChord) -> Self {
+        chord.to_string()
+    }
+}
+
+/// Which key is bound to each action, with the defaults replaced by anything set in the config file
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyMap {
+    bindings: BTreeMap<Action, KeyChord>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        KeyMap {
+            bindings: Action::ALL.iter().map(|action| (*action, action.default_chord())).collect(),
+        }
+    }
+}
+
+impl KeyMap {
+    /// Applies the bindings from the config file, failing if any key would do more than one thing on the same screen
+    pub fn new(overrides: &BTreeMap<Action, KeyChord>) -> Result<KeyMap, CommitBlockError> {
+        let mut key_map = KeyMap::default();
+        key_map.bindings.extend(overrides);
+
+        let mut conflicts = BTreeSet::new();
+        for scope in SCOPES {
+            let actions: Vec<Action> = Action::ALL.into_iter().filter(|action| action.scopes().contains(&scope)).collect();
+            for (index, action) in actions.iter().enumerate() {
+                let chord = key_map.chord(*action);
+                if let Some(other) = actions[..index].iter().find(|other| key_map.chord(**other) == chord) {
+                    conflicts.insert(format!("`{}` and `{}` are both bound to {}", other.name(), action.name(), chord));
+                }
+                if scope.reserved_keys().contains(&chord) {
+                    conflicts.insert(format!("`{}` is bound to {}, which is already used on the {:?} screen", action.name(), chord, scope));
+                }
+                if scope.takes_text() && chord.is_typed() {
+                    conflicts.insert(format!("`{}` is bound to {}, which needs Ctrl or Alt as it's used while typing", action.name(), chord));
+                }
+            }
+        }
+
+        if conflicts.is_empty() {
+            Ok(key_map)
+        } else {
+            Err(CommitBlockError::KeyBindings(conflicts.into_iter().collect::<Vec<_>>().join("; ")))
+        }
+    }
+
+    pub fn chord(&self, action: Action) -> KeyChord {
+        self.bindings.get(&action).copied().unwrap_or_else(|| action.default_chord())
+    }
+
+    pub fn matches(&self, action: Action, key: &KeyEvent) -> bool {
+        self.chord(action) == KeyChord::new(key.code, key.modifiers)
+    }
+
+    /// The action the key is bound to on the given screen, if any
+    pub fn action(&self, scope: Scope, key: &KeyEvent) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.scopes().contains(&scope) && self.matches(*action, key))
+    }
+
+    /// Describes what each key does, in the form used in the footer, e.g. `(q) quit (h) help`
+    pub fn hints(&self, hints: &[(Action, &str)]) -> String {
+        hints.iter()
+            .map(|(action, description)| format!("({}) {}", self.chord(*action), description))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Which of the hints from `hints` is shown at the given column
+    pub fn hint_at(&self, hints: &[(Action, &str)], column: usize) -> Option<Action> {
+        let mut start = 0;
+        for (action, description) in hints {
+            let end = start + format!("({}) {}", self.chord(*action), description).chars().count();
+            if (start..end).contains(&column) {
+                return Some(*action);
+            }
+            start = end + 1;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyEventKind;
+
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new_with_kind(code, modifiers, KeyEventKind::Press)
+    }
+
+    #[test]
+    fn can_parse_chords() {
+        assert_eq!("q".parse(), Ok(KeyChord::char('q')));
+        assert_eq!("Ctrl-r".parse(), Ok(KeyChord::ctrl('r')));
+        assert_eq!("ctrl+-".parse(), Ok(KeyChord::ctrl('-')));
+        assert_eq!("shift-tab".parse(), Ok(KeyChord::new(KeyCode::Tab, KeyModifiers::SHIFT)));
+        assert_eq!("F5".parse(), Ok(KeyChord::new(KeyCode::F(5), KeyModifiers::NONE)));
+        assert_eq!("space".parse(), Ok(KeyChord::char(' ')));
+        assert!("hyper-q".parse::<KeyChord>().is_err());
+        assert!("c-q".parse::<KeyChord>().is_err());
+        assert!("control-q".parse::<KeyChord>().is_err());
+        assert!("meta-q".parse::<KeyChord>().is_err());
+        assert!("f13".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn chords_are_displayed_as_they_can_be_parsed() {
+        for chord in ["q", "Ctrl-r", "Alt-Enter", "Esc", "Space", "PageDown", "F1"] {
+            assert_eq!(chord.parse::<KeyChord>().unwrap().to_string(), chord);
+        }
+    }
+
+    #[test]
+    fn bindings_replace_defaults() {
+        let overrides = BTreeMap::from([(Action::Quit, KeyChord::char('x')), (Action::Cancel, KeyChord::ctrl('c'))]);
+
+        let key_map = KeyMap::new(&overrides).unwrap();
+
+        assert_eq!(key_map.action(Scope::Main, &key(KeyCode::Char('x'), KeyModifiers::NONE)), Some(Action::Quit));
+        assert_eq!(key_map.action(Scope::Main, &key(KeyCode::Char('q'), KeyModifiers::NONE)), None);
+        assert!(key_map.matches(Action::Cancel, &key(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert_eq!(key_map.hints(&[(Action::Quit, "quit"), (Action::Redo, "redo")]), "(x) quit (Ctrl-r) redo");
+    }
+
+    #[test]
+    fn can_find_hint_at_column() {
+        let key_map = KeyMap::default();
+        let hints = [(Action::Quit, "quit"), (Action::Redo, "redo")];
+
+        assert_eq!(key_map.hint_at(&hints, 0), Some(Action::Quit));
+        assert_eq!(key_map.hint_at(&hints, 7), Some(Action::Quit));
+        assert_eq!(key_map.hint_at(&hints, 8), None);
+        assert_eq!(key_map.hint_at(&hints, 9), Some(Action::Redo));
+        assert_eq!(key_map.hint_at(&hints, 30), None);
+    }
+
+    #[test]
+    fn shifted_characters_match_without_shift() {
+        let key_map = KeyMap::new(&BTreeMap::from([(Action::Help, KeyChord::char('?'))])).unwrap();
+
+        assert!(key_map.matches(Action::Help, &key(KeyCode::Char('?'), KeyModifiers::SHIFT)));
+        assert!(!key_map.matches(Action::Refresh, &key(KeyCode::Char('r'), KeyModifiers::CONTROL)));
+    }
+
+    #[test]
+    fn conflicting_bindings_are_rejected() {
+        let conflicts = [
+            (Action::Help, KeyChord::char('q')),
+            (Action::Save, KeyChord::char('s')),
+            (Action::EditHost, KeyChord::new(KeyCode::Up, KeyModifiers::NONE)),
+            (Action::Filter, KeyChord::new(KeyCode::PageDown, KeyModifiers::NONE)),
+        ];
+
+        for (action, chord) in conflicts {
+            let result = KeyMap::new(&BTreeMap::from([(action, chord)]));
+            assert!(matches!(result, Err(CommitBlockError::KeyBindings(_))), "{:?} shouldn't be bindable to {}", action, chord);
+        }
+    }
+
+    #[test]
+    fn keys_can_be_reused_on_different_screens() {
+        assert!(KeyMap::new(&BTreeMap::from([(Action::TestConnection, KeyChord::ctrl('e'))])).is_ok());
+        assert!(KeyMap::new(&BTreeMap::new()).is_ok());
+        assert!(KeyMap::new(&BTreeMap::from([(Action::Confirm, KeyChord::char('n'))])).is_ok());
+    }
+}
@@ -1,35 +1,73 @@
-use std::{error::Error, fs, io, thread};
-use std::env;
+use std::{env, error::Error, fs, io, panic, process};
+use std::collections::BTreeMap;
 use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Read, Write};
-use std::sync::{Arc, mpsc, Mutex};
-use std::sync::mpsc::{Receiver, TryRecvError};
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use chrono::{Local, NaiveDate, Utc};
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use clap::Parser;
 use dotenv::dotenv;
 use ratatui::{
     backend::{Backend, CrosstermBackend},
+    layout::{Margin, Position, Rect},
     crossterm::{
-        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind},
         execute,
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     },
     Terminal,
 };
-use reqwest::header;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use tokio::runtime::{self, Handle};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::{self, error::TryRecvError, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
 use crate::{
     app::{App, CurrentScreen},
-    ui::ui,
+    ui::{footer_action_at, help_section_at, ui, ScreenLayout},
 };
-use crate::app::EditingConfigField;
+use crate::app::{hosts_enforcement, BlockState, ConnectionTest, EditingConfigField, HostState, TokenField};
+use crate::bundles::{blocked_hosts, custom_hosts, BUNDLES};
+use crate::cli::{Args, Command, HistoryCommand, HookEvent, HooksCommand, HostsCommand};
+use crate::daemon::{bind_socket, send_command, serve_socket, serve_webhook, CommandHandler, DaemonCommand};
+use crate::hooks::install_hooks;
+use crate::host_list::{format_host_list, parse_host_list};
+use crate::hostname::{normalise_host, remove_duplicates};
+use crate::day::ContributionDay;
+use crate::error::CommitBlockError;
+use crate::github::{build_http_client, check_connection, fetch_contribution_calendar, CalendarDay, ConnectionReport, GitHubClient, RateLimit};
+use crate::history::{load_history, persist_history};
+use crate::keys::{Action, KeyChord, KeyMap, Scope, HELP_KEY};
+use crate::theme::{Theme, ThemeName};
+use crate::hosts_file::HostsFile;
+use crate::paths::Paths;
+use crate::token::TokenStore;
 use crate::HostToggleOption::{BLOCK, UNBLOCK};
 
 mod app;
+mod bundles;
+mod cli;
+mod daemon;
+mod day;
+mod error;
+mod github;
+mod help;
+mod history;
+mod keys;
+mod hooks;
+mod host_list;
+mod hostname;
+mod hosts_file;
+mod paths;
+mod theme;
+mod token;
 mod ui;
 
+/// Location of the config file used by earlier versions, relative to the working directory
 const CONFIG_FILE_PATH: &str = "config.toml";
 const HOST_FILE_LOCAL_PREFIX_IP4: &str = "127.0.0.1\t";
 const HOST_FILE_LOCAL_PREFIX_IP6: &str = "::1\t\t";
@@ -39,35 +77,32 @@ const HOST_FILE_LOCAL_PREFIX_DISABLED_IP6: &str = "#::1\t\t";
 const HOST_FILE_COMMIT_BLOCK_BEGIN: &str = "### CommitBlock";
 const HOST_FILE_COMMIT_BLOCK_END: &str = "### End CommitBlock";
 const HOST_FILE_PATH: &str = "/etc/hosts";
+/// Location of the state file used by earlier versions, relative to the working directory
 const STATE_FILE_PATH: &str = "tmp/state_file.json";
-const QUIT_KEY: char = 'q';
-const INSERT_KEY: char = 'i';
-const HELP_KEY: char = 'h';
-const CONFIGURATION_KEY: char = 'c';
+/// How long each bar of today's progress on the history screen covers
+const PROGRESS_INTERVAL_MINUTES: i64 = 30;
+/// How many weeks of contributions are shown on the history screen, including the current one. GitHub won't return
+/// more than a year at once.
+const HISTORY_WEEKS: u64 = 52;
 const DATE_FORMATTER: &str = "%Y-%m-%d";
-const GH_API_PATH: &str = "https://api.github.com/graphql";
-const GRAPHQL_QUERY: &str = r#"
-        query($userName:String!) {
-          user(login: $userName){
-            contributionsCollection {
-              contributionCalendar {
-                totalContributions
-                weeks {
-                  contributionDays {
-                    contributionCount
-                    date
-                  }
-                }
-              }
-            }
-          }
-        }
-       "#;
-
-struct RequestModel {
-    token: String,
-    body: Value,
-}
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 60;
+/// Shorter configured poll intervals are raised to this, so a typo can't send a request to GitHub in a tight loop.
+/// Refreshing, git hooks and the webhook can still be used to check straight away.
+const MIN_POLL_INTERVAL_SECS: u64 = 60;
+/// How much less often to poll once the contribution goal has been met
+const GOAL_MET_POLL_INTERVAL_MULTIPLIER: u32 = 6;
+/// How much less often to poll after being rate limited, if GitHub didn't say when the limit resets
+const RATE_LIMITED_POLL_INTERVAL_MULTIPLIER: u32 = 12;
+/// Once fewer than this percentage of requests remain, polling is slowed down until the rate limit resets
+const LOW_RATE_LIMIT_PERCENT: u32 = 10;
+/// Used to unlock the encrypted secrets file on startup, so the passphrase doesn't need to be entered each time
+const PASSPHRASE_ENV_VAR: &str = "COMMIT_BLOCK_PASSPHRASE";
+/// Turns off colour when set to anything, as described at https://no-color.org
+const NO_COLOR_ENV_VAR: &str = "NO_COLOR";
+/// How often to check the hosts file for changes, including those made outside Commit Block
+const HOSTS_FILE_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+/// How long to wait for background tasks to finish when quitting
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Serialize, Deserialize)]
 struct ContributionThresholdStatus {
@@ -75,116 +110,734 @@ struct ContributionThresholdStatus {
     threshold_met_goal: Option<u32>,
 }
 
+/// The most recently fetched contribution count, used when GitHub can't be reached
+#[derive(Serialize, Deserialize)]
+struct ContributionCache {
+    date: String,
+    contribution_count: u32,
+    fetched_at: DateTime<Local>,
+}
+
 #[derive(Deserialize, Serialize)]
 struct Config {
     github_username: String,
     contribution_goal: u32,
+    #[serde(default = "default_poll_interval_secs")]
+    poll_interval_secs: u64,
+    #[serde(default)]
+    offline_policy: OfflinePolicy,
+    /// File containing the GitHub token, which must not be readable by other users
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token_file: Option<PathBuf>,
+    /// Timezone in which days are measured, e.g. `Europe/London`. The system timezone is used if this isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timezone: Option<Tz>,
+    /// Time at which each day starts, e.g. `04:00`. Contributions made before this count towards the previous day
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    day_start: Option<NaiveTime>,
+    /// Port on localhost on which to listen for `POST /refresh` requests. No listener is started if this isn't set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    webhook_port: Option<u16>,
+    /// Names of the built-in bundles of hosts to block, e.g. `social`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    bundles: Vec<String>,
+    /// Colours to draw the interface with. Colour is turned off altogether if `NO_COLOR` is set
+    #[serde(default)]
+    theme: ThemeName,
+    /// Whether to capture the mouse, so that hosts and fields can be clicked. Turning this off lets text be selected in
+    /// the terminal as usual
+    #[serde(default = "default_mouse")]
+    mouse: bool,
+    /// Keys to use instead of the defaults, e.g. `quit = "ctrl-q"`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    keys: BTreeMap<Action, KeyChord>,
+}
+
+/// What to do with the hosts when contribution progress can't be fetched from GitHub
+#[derive(Deserialize, Serialize, Default, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+enum OfflinePolicy {
+    /// Leave the hosts unblocked if the goal had already been met today
+    #[default]
+    StayUnblocked,
+    /// Block the hosts until progress can be fetched again
+    FailClosed,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            github_username: "".to_string(),
+            contribution_goal: 1,
+            poll_interval_secs: DEFAULT_POLL_INTERVAL_SECS,
+            offline_policy: OfflinePolicy::default(),
+            token_file: None,
+            timezone: None,
+            day_start: None,
+            webhook_port: None,
+            bundles: Vec::new(),
+            theme: ThemeName::default(),
+            mouse: true,
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    fn contribution_day(&self) -> ContributionDay {
+        ContributionDay::new(self.timezone, self.day_start)
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_secs.max(MIN_POLL_INTERVAL_SECS))
+    }
+}
+
+fn default_poll_interval_secs() -> u64 {
+    DEFAULT_POLL_INTERVAL_SECS
+}
+
+fn default_mouse() -> bool {
+    true
+}
+
+/// The result of a successful poll of contribution progress
+struct PollOutcome {
+    contribution_count: u32,
+    goal_met: bool,
+    rate_limit: Option<RateLimit>,
+    poll_interval: Duration,
+    fetched_at: DateTime<Local>,
+    /// Set if the progress couldn't be fetched, and the last known value was used instead
+    fetch_error: Option<CommitBlockError>,
+}
+
+/// Messages sent from the background tasks to the UI
+enum PollerMessage {
+    Progress {
+        contribution_count: u32,
+        fetched_at: DateTime<Local>,
+        /// Whether the count is the last known value, because it couldn't be fetched
+        stale: bool,
+    },
+    /// Whether each host's entries in the hosts file are active
+    HostStates(BTreeMap<String, HostState>),
+    /// Every host in the hosts file, which changes when hosts are imported from the command line
+    Hosts(Vec<String>),
+    /// A commit was reported by a git hook, which GitHub won't have counted yet
+    LocalCommit,
+    Schedule {
+        next_poll_at: DateTime<Local>,
+        rate_limit: Option<RateLimit>,
+    },
+    /// Contributions made on each day of the last year, for the history screen
+    Calendar(Result<Vec<CalendarDay>, CommitBlockError>),
+    /// The result of testing the token and whether the user exists
+    Connection {
+        username: String,
+        result: Result<ConnectionReport, CommitBlockError>,
+    },
+    Error(CommitBlockError),
+}
+
+/// Handles to the hosts file, configuration and background tasks which the UI acts on
+struct Context {
+    hosts_file: Arc<HostsFile>,
+    paths: Paths,
+    token_store: Arc<TokenStore>,
+    runtime: Handle,
+    /// Wakes the poller to check contribution progress straight away
+    refresh: Arc<Notify>,
+    /// Lets work started from the UI report back once it's done
+    messages: UnboundedSender<PollerMessage>,
 }
 
 /// Used to signify whether to block or unblock the list of configured hosts
+#[allow(clippy::upper_case_acronyms)]
 enum HostToggleOption {
     BLOCK,
     UNBLOCK,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
+    dotenv().ok();
+    let args = Args::parse();
+    let paths = match Paths::resolve(&args) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    if let Some(command) = &args.command {
+        if let Err(e) = run_command(command, &paths, args.dry_run) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return Ok(());
+    }
+
+    paths.migrate_legacy_files(Path::new(CONFIG_FILE_PATH), Path::new(STATE_FILE_PATH))?;
+    fs::create_dir_all(&paths.state_dir)?;
+
+    let hosts_file = Arc::new(if args.dry_run {
+        HostsFile::dry_run(&paths.hosts_file)
+    } else {
+        HostsFile::new(&paths.hosts_file)
+    });
+
+    let token_store = Arc::new(TokenStore::new(paths.secrets_file()));
+    let app = init_app(&hosts_file, &paths, &token_store)?;
+    let mut app = app.lock().unwrap();
+
+    let runtime = runtime::Builder::new_multi_thread().enable_all().build()?;
+    let shutdown = CancellationToken::new();
+    let refresh = Arc::new(Notify::new());
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    let github_client = GitHubClient::new(build_http_client()?);
+    runtime.spawn(run_poller(tx.clone(), github_client, Arc::clone(&hosts_file), paths.clone(), Arc::clone(&token_store), Arc::clone(&refresh), shutdown.clone()));
+    runtime.spawn(watch_hosts_file(tx.clone(), Arc::clone(&hosts_file), shutdown.clone()));
+    let command_handler: CommandHandler = {
+        let refresh = Arc::clone(&refresh);
+        let tx = tx.clone();
+        Arc::new(move |command| match command {
+            DaemonCommand::Refresh | DaemonCommand::Push => refresh.notify_one(),
+            DaemonCommand::Commit => {
+                let _ = tx.send(PollerMessage::LocalCommit);
+            }
+        })
+    };
+
+    // Failing to listen for commands is reported rather than stopping the application, as blocking still works without
+    let runtime_context = runtime.enter();
+    match bind_socket(&paths.socket_file()) {
+        Ok(listener) => {
+            let socket_file = paths.socket_file();
+            let (command_handler, shutdown) = (Arc::clone(&command_handler), shutdown.clone());
+            runtime.spawn(async move { serve_socket(listener, &socket_file, command_handler, shutdown).await });
+        }
+        Err(e) => app.report_error(&e),
+    }
+    if let Some(port) = try_load_config(&paths.config_file).unwrap_or_default().webhook_port {
+        match runtime.block_on(TcpListener::bind(("127.0.0.1", port))) {
+            Ok(listener) => {
+                runtime.spawn(serve_webhook(listener, command_handler, shutdown.clone()));
+            }
+            Err(e) => app.report_error(&CommitBlockError::Io(e)),
+        }
+    }
+
+    install_panic_hook();
     enable_raw_mode()?;
     let mut stderr = io::stderr();
-    execute!(stderr, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stderr, EnterAlternateScreen)?;
+    if try_load_config(&paths.config_file).unwrap_or_default().mouse {
+        execute!(stderr, EnableMouseCapture)?;
+    }
 
     let backend = CrosstermBackend::new(stderr);
     let mut terminal = Terminal::new(backend)?;
 
-    let app = init_app();
-    let (tx, rx): (mpsc::Sender<u32>, Receiver<u32>) = mpsc::channel();
-
-    thread::spawn(move || {
-        loop {
-            let configuration = load_config(CONFIG_FILE_PATH);
-            let mut state = load_contribution_state(STATE_FILE_PATH).unwrap_or_else(|| ContributionThresholdStatus {
-                threshold_met_date: None,
-                threshold_met_goal: None,
-            });
-
-            let today = Local::now().date_naive();
-            if let Some(stored_date) = &state.threshold_met_date {
-                let stored_date = NaiveDate::parse_from_str(stored_date, DATE_FORMATTER).unwrap();
-
-                // * If the goal has been met earlier than today, reset the state
-                // * If the goal has been met today, but the configuration has been updated to increase
-                // the contribution target, reset the state
-                if stored_date < today || state.threshold_met_goal.unwrap_or(0) < configuration.contribution_goal {
-                    state.threshold_met_date = None;
-                    state.threshold_met_goal = None;
-                    modify_hosts(BLOCK).expect("TODO: panic message");
-                } else {
-                    let contribution_count = check_contribution_progress(&configuration);
-                    if contribution_count >= configuration.contribution_goal.clone() {
-                        record_contribution_goal_met(today, state, &configuration);
-                    }
-                    if tx.send(contribution_count).is_err() { // Mark the progress bar as complete
+    let context = Context {
+        hosts_file,
+        paths,
+        token_store,
+        runtime: runtime.handle().clone(),
+        refresh,
+        messages: tx,
+    };
+    let result = run_app(&mut terminal, &mut app, rx, &context);
+
+    // Stop the background tasks rather than waiting for their next wake up
+    shutdown.cancel();
+    drop(runtime_context);
+    runtime.shutdown_timeout(SHUTDOWN_TIMEOUT);
+
+    restore_terminal()?;
+    result?;
+
+    Ok(())
+}
+
+/// Runs a subcommand, most of which are sent to the running instance
+fn run_command(command: &Command, paths: &Paths, dry_run: bool) -> Result<(), CommitBlockError> {
+    match command {
+        Command::Refresh => {
+            send_command(&paths.socket_file(), &DaemonCommand::Refresh)?;
+            println!("Refresh requested");
+        }
+        Command::Hooks { command: HooksCommand::Install { repo, force } } => {
+            for hook_file in install_hooks(repo, &env::current_exe()?, &paths.state_dir, *force)? {
+                println!("Installed {}", hook_file.display());
+            }
+        }
+        Command::Hosts { command: HostsCommand::Import { file, format } } => {
+            let hosts_file = if dry_run { HostsFile::dry_run(&paths.hosts_file) } else { HostsFile::new(&paths.hosts_file) };
+            let config = try_load_config(&paths.config_file)?;
+            let blocked = initialise_hosts(&hosts_file)?;
+            let mut imported: Vec<String> = Vec::new();
+            for entry in parse_host_list(file, &fs::read_to_string(file)?, *format)? {
+                match normalise_host(&entry) {
+                    Ok(host) if !blocked.contains(&host) && !imported.contains(&host) => imported.push(host),
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Skipping entry: {}", e),
+                }
+            }
+
+            let mut hosts = custom_hosts(blocked, &config.bundles);
+            hosts.extend(imported.iter().cloned());
+            update_hosts(&hosts_file, &hosts, &config.bundles)?;
+            println!("Imported {} hosts", imported.len());
+            if let Some(diff) = hosts_file.last_diff() {
+                print!("{}", diff);
+            }
+        }
+        Command::Hosts { command: HostsCommand::Export { file, format } } => {
+            let config = try_load_config(&paths.config_file)?;
+            let hosts = custom_hosts(initialise_hosts(&HostsFile::new(&paths.hosts_file))?, &config.bundles);
+            let content = format_host_list(&hosts, *format);
+            match file {
+                Some(file) => fs::write(file, content)?,
+                None => print!("{}", content),
+            }
+        }
+        Command::History { command: HistoryCommand::Export { file } } => {
+            let csv = load_history(paths.history_file()).to_csv();
+            match file {
+                Some(file) => fs::write(file, csv)?,
+                None => print!("{}", csv),
+            }
+        }
+        Command::Notify { event } => {
+            let command = match event {
+                HookEvent::Commit => DaemonCommand::Commit,
+                HookEvent::Push => DaemonCommand::Push,
+            };
+            send_command(&paths.socket_file(), &command)?;
+        }
+    }
+    Ok(())
+}
+
+/// Restores the terminal before the panic message is printed, so that it isn't left in raw mode
+fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        default_hook(info);
+    }));
+}
+
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture)
+}
+
+/// Repeatedly polls contribution progress, sending the results to the UI until it's closed or shutdown is requested
+async fn run_poller(tx: UnboundedSender<PollerMessage>, mut github_client: GitHubClient, hosts_file: Arc<HostsFile>, paths: Paths, token_store: Arc<TokenStore>, refresh: Arc<Notify>, shutdown: CancellationToken) {
+    let mut poll_interval = Duration::from_secs(DEFAULT_POLL_INTERVAL_SECS);
+    let mut rate_limit = None;
+
+    loop {
+        // The only point at which a poll can be cancelled is while waiting for GitHub, before the fetched progress
+        // has been acted on, so the hosts and state files are never left half updated
+        let result = tokio::select! {
+            _ = shutdown.cancelled() => break,
+            result = poll_contribution_progress(&mut github_client, &hosts_file, &paths, &token_store) => result,
+        };
+
+        let (message, delay) = match result {
+            Ok(outcome) => {
+                poll_interval = outcome.poll_interval;
+                rate_limit = outcome.rate_limit.or(rate_limit);
+                let delay = match &outcome.fetch_error {
+                    Some(e) => error_poll_delay(e, poll_interval),
+                    None => next_poll_delay(poll_interval, outcome.goal_met, rate_limit.as_ref(), Utc::now()),
+                };
+                let stale = outcome.fetch_error.is_some();
+                if let Some(e) = outcome.fetch_error {
+                    if tx.send(PollerMessage::Error(e)).is_err() {
                         break;
                     }
-                    thread::sleep(Duration::from_secs(30));
-                    continue;
                 }
+                (PollerMessage::Progress { contribution_count: outcome.contribution_count, fetched_at: outcome.fetched_at, stale }, delay)
             }
+            Err(e) => {
+                let delay = error_poll_delay(&e, poll_interval);
+                (PollerMessage::Error(e), delay)
+            }
+        };
+
+        let schedule = PollerMessage::Schedule {
+            next_poll_at: Local::now() + delay,
+            rate_limit: rate_limit.clone(),
+        };
+
+        if tx.send(message).is_err() || tx.send(schedule).is_err() {
+            break; // Exit if the receiver has been dropped
+        }
+
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(delay) => {}
+            _ = refresh.notified() => {}
+        }
+    }
+}
+
+/// Reports the hosts and whether each is blocked as they actually are in the hosts file, rather than what they're expected
+/// to be. The file is read once each time, and only changes are sent to the UI, so that edits made outside Commit Block
+/// are picked up without repeating the same state.
+async fn watch_hosts_file(tx: UnboundedSender<PollerMessage>, hosts_file: Arc<HostsFile>, shutdown: CancellationToken) {
+    let mut interval = tokio::time::interval(HOSTS_FILE_WATCH_INTERVAL);
+    let mut last_hosts = None;
+    let mut last_host_states = None;
+    let mut last_error = None;
+
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = interval.tick() => {}
+        }
 
-            let contribution_count = check_contribution_progress(&configuration);
-            if contribution_count >= configuration.contribution_goal.clone() {
-                record_contribution_goal_met(today, state, &configuration);
+        let mut messages = Vec::new();
+        match hosts_file.read() {
+            Ok(content) => {
+                let (hosts, host_states) = (parse_hosts(&content), parse_host_states(&content));
+                last_error = None;
+                if last_hosts.as_ref() != Some(&hosts) {
+                    last_hosts = Some(hosts.clone());
+                    messages.push(PollerMessage::Hosts(hosts));
+                }
+                if last_host_states.as_ref() != Some(&host_states) {
+                    last_host_states = Some(host_states.clone());
+                    messages.push(PollerMessage::HostStates(host_states));
+                }
             }
+            Err(e) if last_error.as_ref() == Some(&e.to_string()) => {}
+            Err(e) => {
+                last_hosts = None;
+                last_host_states = None;
+                last_error = Some(e.to_string());
+                messages.push(PollerMessage::Error(CommitBlockError::HostsFile(e)));
+            }
+        }
 
-            if tx.send(contribution_count).is_err() {
-                break; // Exit if the receiver has been dropped
+        for message in messages {
+            if tx.send(message).is_err() {
+                return;
             }
+        }
+    }
+}
 
-            thread::sleep(Duration::from_secs(5));
+/// Works out how long to wait before polling again after failing to fetch contribution progress
+fn error_poll_delay(error: &CommitBlockError, poll_interval: Duration) -> Duration {
+    match error {
+        CommitBlockError::RateLimited { reset_at: Some(reset_at) } => {
+            poll_interval.max((*reset_at - Utc::now()).to_std().unwrap_or_default())
         }
+        CommitBlockError::RateLimited { reset_at: None } => poll_interval * RATE_LIMITED_POLL_INTERVAL_MULTIPLIER,
+        _ => poll_interval,
+    }
+}
+
+/// Works out how long to wait before polling again. Once the goal has been met there's less need to check as
+/// often, and when the rate limit is running low the remaining requests are spread out until it resets.
+fn next_poll_delay(poll_interval: Duration, goal_met: bool, rate_limit: Option<&RateLimit>, now: DateTime<Utc>) -> Duration {
+    let delay = if goal_met {
+        poll_interval * GOAL_MET_POLL_INTERVAL_MULTIPLIER
+    } else {
+        poll_interval
+    };
+
+    let Some(rate_limit) = rate_limit else {
+        return delay;
+    };
+    let until_reset = (rate_limit.reset_at - now).to_std().unwrap_or_default();
+
+    if rate_limit.remaining == 0 {
+        delay.max(until_reset)
+    } else if rate_limit.remaining * 100 < rate_limit.limit * LOW_RATE_LIMIT_PERCENT {
+        delay.max(until_reset / rate_limit.remaining)
+    } else {
+        delay
+    }
+}
+
+/// Checks today's contribution progress, updating the hosts file if the goal has been met or needs to be reset
+async fn poll_contribution_progress(github_client: &mut GitHubClient, hosts_file: &HostsFile, paths: &Paths, token_store: &TokenStore) -> Result<PollOutcome, CommitBlockError> {
+    let configuration = try_load_config(&paths.config_file)?;
+    let mut state = load_contribution_state(paths.state_file()).unwrap_or(ContributionThresholdStatus {
+        threshold_met_date: None,
+        threshold_met_goal: None,
     });
-    let mut app = app.lock().unwrap();
-    run_app(&mut terminal, &mut app, rx).expect("TODO: panic message");
 
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
+    let contribution_day = configuration.contribution_day();
+    let today = contribution_day.today();
+    let mut goal_already_met = false;
+    if let Some(stored_date) = &state.threshold_met_date {
+        let stored_date = NaiveDate::parse_from_str(stored_date, DATE_FORMATTER)?;
+
+        // * If the goal has been met earlier than today, reset the state
+        // * If the goal has been met today, but the configuration has been updated to increase
+        // the contribution target, reset the state
+        if stored_date < today || state.threshold_met_goal.unwrap_or(0) < configuration.contribution_goal {
+            state.threshold_met_date = None;
+            state.threshold_met_goal = None;
+            modify_hosts(hosts_file, BLOCK).map_err(CommitBlockError::HostsFile)?;
+        } else {
+            goal_already_met = true;
+        }
+    }
 
-    Ok(())
+    let (from, to) = contribution_day.bounds(today);
+    let result = match token_store.resolve(configuration.token_file.as_deref()) {
+        Ok((token, _)) => github_client.check_contribution_progress(&configuration.github_username, &token, from, to).await,
+        Err(e) => Err(e),
+    };
+    let progress = match result {
+        Ok(progress) => progress,
+        Err(e) if e.is_api_error() => return poll_offline(hosts_file, paths, &configuration, today, goal_already_met, e),
+        Err(e) => return Err(e),
+    };
+
+    let fetched_at = Local::now();
+    persist_contribution_cache(paths.cache_file(), &ContributionCache {
+        date: today.format(DATE_FORMATTER).to_string(),
+        contribution_count: progress.contribution_count,
+        fetched_at,
+    })?;
+    let mut history = load_history(paths.history_file());
+    if history.record_progress(today, fetched_at, progress.contribution_count) {
+        persist_history(paths.history_file(), &history)?;
+    }
+
+    let goal_met = progress.contribution_count >= configuration.contribution_goal;
+    if goal_met {
+        record_contribution_goal_met(hosts_file, paths, today, state, &configuration)?;
+    }
+
+    Ok(PollOutcome {
+        contribution_count: progress.contribution_count,
+        goal_met: goal_met || goal_already_met,
+        rate_limit: progress.rate_limit,
+        poll_interval: configuration.poll_interval(),
+        fetched_at,
+        fetch_error: None,
+    })
 }
 
-fn init_app() -> Arc<Mutex<App>> {
-    let existing_hosts = initialise_hosts();
+/// Falls back to the last known progress for today when contributions can't be fetched. Depending on the configured
+/// policy, the hosts either stay unblocked if the goal had already been met, or are blocked until progress can be
+/// confirmed again.
+fn poll_offline(hosts_file: &HostsFile, paths: &Paths, configuration: &Config, today: NaiveDate, goal_already_met: bool, error: CommitBlockError) -> Result<PollOutcome, CommitBlockError> {
+    if configuration.offline_policy == OfflinePolicy::FailClosed {
+        modify_hosts(hosts_file, BLOCK).map_err(CommitBlockError::HostsFile)?;
+    }
+
+    let today = today.format(DATE_FORMATTER).to_string();
+    let Some(cache) = load_contribution_cache(paths.cache_file()).filter(|cache| cache.date == today) else {
+        return Err(error);
+    };
+
+    Ok(PollOutcome {
+        contribution_count: cache.contribution_count,
+        goal_met: goal_already_met && configuration.offline_policy == OfflinePolicy::StayUnblocked,
+        rate_limit: None,
+        poll_interval: configuration.poll_interval(),
+        fetched_at: cache.fetched_at,
+        fetch_error: Some(error),
+    })
+}
 
-    let configuration = load_config(CONFIG_FILE_PATH);
+fn init_app(hosts_file: &HostsFile, paths: &Paths, token_store: &TokenStore) -> Result<Arc<Mutex<App>>, CommitBlockError> {
+    let configuration = try_load_config(&paths.config_file)?;
+    let keys = KeyMap::new(&configuration.keys)?;
+
+    // Bundles may have been enabled in the config file, or gained hosts since the hosts file was last written
+    let blocked = initialise_hosts(hosts_file)?;
+    let existing_hosts = custom_hosts(blocked.clone(), &configuration.bundles);
+    if blocked_hosts(&existing_hosts, &configuration.bundles) != blocked {
+        update_hosts(hosts_file, &existing_hosts, &configuration.bundles)?;
+    }
     let contribution_goal = configuration.contribution_goal;
     let username = configuration.github_username.clone();
 
-    let state = load_contribution_state(STATE_FILE_PATH).unwrap_or_else(|| ContributionThresholdStatus {
+    let state = load_contribution_state(paths.state_file()).unwrap_or(ContributionThresholdStatus {
         threshold_met_date: None,
         threshold_met_goal: None,
     });
     let threshold_met_date = state.threshold_met_date.clone();
     let threshold_met_goal = state.threshold_met_goal;
 
-    Arc::new(Mutex::new(App::new(
+    let mut app = App::new(
         existing_hosts,
         0, // This might not be accurate, but will be corrected by the other thread which is calling GH. Initialising to 0 allows the app to startup instantly instead of waiting for an external response
         contribution_goal,
         username,
         threshold_met_date,
-        threshold_met_goal)))
+        threshold_met_goal);
+    app.dry_run = hosts_file.is_dry_run();
+    app.bundles = configuration.bundles.clone();
+    app.keys = keys;
+    app.theme = Theme::new(configuration.theme, env::var_os(NO_COLOR_ENV_VAR).is_some_and(|value| !value.is_empty()));
+
+    if token_store.has_secrets_file() {
+        if let Ok(passphrase) = env::var(PASSPHRASE_ENV_VAR) {
+            if let Err(e) = token_store.unlock(&passphrase) {
+                app.report_error(&e);
+            }
+        }
+    }
+    app.token_status = Some(describe_token(token_store, configuration.token_file.as_deref()));
+
+    Ok(Arc::new(Mutex::new(app)))
+}
+
+/// Fetches the contributions made on each day of the last year, starting on a Sunday so that the weeks line up
+async fn fetch_calendar(tx: UnboundedSender<PollerMessage>, paths: Paths, token_store: Arc<TokenStore>) {
+    let result = async {
+        let configuration = try_load_config(&paths.config_file)?;
+        let (token, _) = token_store.resolve(configuration.token_file.as_deref())?;
+        let contribution_day = configuration.contribution_day();
+        let today = contribution_day.today();
+        let first_day = today - Days::new((HISTORY_WEEKS - 1) * 7 + u64::from(today.weekday().num_days_from_sunday()));
+        let (from, _) = contribution_day.bounds(first_day);
+        let (_, to) = contribution_day.bounds(today);
+        fetch_contribution_calendar(&build_http_client()?, &configuration.github_username, &token, from, to).await
+    }.await;
+    let _ = tx.send(PollerMessage::Calendar(result));
+}
+
+/// Checks that the current token works, and whether the user exists
+async fn test_connection(tx: UnboundedSender<PollerMessage>, paths: Paths, token_store: Arc<TokenStore>, username: String) {
+    let result = async {
+        let config = try_load_config(&paths.config_file)?;
+        let (token, _) = token_store.resolve(config.token_file.as_deref())?;
+        check_connection(&build_http_client()?, &username, &token).await
+    }.await;
+    let _ = tx.send(PollerMessage::Connection { username, result });
+}
+
+/// Tests the connection in the background, so the UI keeps responding while waiting for GitHub
+fn start_connection_test(app: &mut App, context: &Context, username: String, save: bool) {
+    app.connection_status = Some(String::from("Testing connection to GitHub..."));
+    app.connection_test = Some(ConnectionTest { username: username.clone(), save });
+    context.runtime.spawn(test_connection(context.messages.clone(), context.paths.clone(), Arc::clone(&context.token_store), username));
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App, rx: Receiver<u32>) -> io::Result<bool> {
+/// Shows the result of a connection test, then finishes saving if that's what it was started for. Results are ignored
+/// if the popup has been closed or the username changed in the meantime.
+fn finish_connection_test(app: &mut App, paths: &Paths, username: String, result: Result<ConnectionReport, CommitBlockError>) {
+    let Some(test) = app.connection_test.take_if(|test| test.username == username) else {
+        return;
+    };
+    if app.editing_config_field.is_none() || app.github_username_input != username {
+        return;
+    }
+
+    app.connection_status = Some(describe_connection(&result, &username));
+    if !test.save {
+        return;
+    }
+    // If the user can't be found, it's only saved if it's saved a second time
+    if matches!(result, Ok(ConnectionReport { user_exists: false, .. })) {
+        app.unverified_username = Some(username);
+    } else if app.current_screen == CurrentScreen::Configuration {
+        save_configuration(app, paths);
+    }
+}
+
+/// Saves the goal and username from the configuration popup and closes it, or resets the goal if it isn't a number
+fn save_configuration(app: &mut App, paths: &Paths) {
+    let Ok(new_goal) = app.contribution_goal_input.parse::<u32>() else {
+        app.contribution_goal_input = app.contribution_goal.to_string();
+        return;
+    };
+    app.unverified_username = None;
+    app.connection_status = None;
+
+    if new_goal != app.contribution_goal || app.github_username_input != app.username {
+        app.record_undo();
+    }
+    app.contribution_goal = new_goal;
+    app.username = app.github_username_input.clone();
+
+    // Save the configuration back to the file, keeping any settings which can't be edited here
+    let mut config = try_load_config(&paths.config_file).unwrap_or_default();
+    config.github_username = app.username.clone();
+    config.contribution_goal = app.contribution_goal;
+    if let Err(e) = save_config(&paths.config_file, &config) {
+        app.report_error(&e);
+    }
+
+    // Reset the editing field
+    app.editing_config_field = None;
+
+    // Return to the main screen
+    app.current_screen = CurrentScreen::Main;
+}
+
+fn describe_connection(result: &Result<ConnectionReport, CommitBlockError>, username: &str) -> String {
+    match result {
+        Ok(report) => report.summary(username),
+        Err(e) => e.to_string(),
+    }
+}
+
+/// Describes where the GitHub token is being read from, or why one couldn't be found
+fn describe_token(token_store: &TokenStore, token_file: Option<&Path>) -> String {
+    match token_store.resolve(token_file) {
+        Ok((_, source)) => format!("Using token from {}", source),
+        Err(CommitBlockError::MissingToken) if token_store.has_secrets_file() => {
+            String::from("Secrets file is locked, enter the passphrase to unlock it")
+        }
+        Err(e) => e.to_string(),
+    }
+}
+
+fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App, mut rx: UnboundedReceiver<PollerMessage>, context: &Context) -> io::Result<bool> {
     loop {
+        app.hosts_diff = context.hosts_file.last_diff();
         terminal.draw(|f| ui(f, app))?;
 
         match rx.try_recv() {
-            Ok(contribution_progress) => {
-                app.progress = contribution_progress;
+            Ok(PollerMessage::Progress { contribution_count, fetched_at, stale }) => {
+                app.progress = contribution_count;
+                app.last_fetch = Some(fetched_at);
+                app.progress_stale = stale;
+                if !stale {
+                    app.api_error = None;
+                    app.reconcile_local_commits(Local::now());
+                }
+            }
+            Ok(PollerMessage::LocalCommit) => {
+                app.record_local_commit(Local::now());
+            }
+            Ok(PollerMessage::HostStates(host_states)) => {
+                app.host_states = Some(host_states);
+            }
+            Ok(PollerMessage::Hosts(hosts)) => {
+                app.replace_hosts_from_file(hosts);
+            }
+            Ok(PollerMessage::Schedule { next_poll_at, rate_limit }) => {
+                app.next_poll_at = Some(next_poll_at);
+                app.rate_limit = rate_limit;
+            }
+            Ok(PollerMessage::Calendar(Ok(calendar))) => {
+                app.calendar = Some(calendar);
+                app.calendar_status = None;
+            }
+            Ok(PollerMessage::Calendar(Err(e))) => {
+                app.calendar_status = Some(e.to_string());
+            }
+            Ok(PollerMessage::Connection { username, result }) => {
+                finish_connection_test(app, &context.paths, username, result);
+            }
+            Ok(PollerMessage::Error(e)) => {
+                app.report_error(&e);
             }
             Err(TryRecvError::Empty) => {}
             Err(TryRecvError::Disconnected) => {
@@ -192,273 +845,438 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App, rx: Receiver<u
             }
         }
 
-        // TODO clean this up
         if event::poll(Duration::from_millis(10))? {
-            if let Ok(evt) = event::read() {
-                match evt {
-                    Event::Key(key) => {
-                        if key.kind == KeyEventKind::Release {
-                            continue;
-                        }
+            let exit = match event::read() {
+                Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => handle_key(app, key, context),
+                Ok(Event::Mouse(mouse)) => {
+                    let size = terminal.size()?;
+                    handle_mouse(app, mouse, Rect::new(0, 0, size.width, size.height), context)
+                }
+                _ => false,
+            };
+            if exit {
+                return Ok(true);
+            }
+        }
+    }
+}
+
+/// Acts on a key press, returning whether to exit
+fn handle_key(app: &mut App, key: KeyEvent, context: &Context) -> bool {
+    let Context { hosts_file, paths, token_store, runtime, refresh, messages } = context;
 
-                        match app.current_screen {
-                            CurrentScreen::Main => match key.code {
-                                KeyCode::Char(INSERT_KEY) => {
-                                    app.current_screen = CurrentScreen::Editing;
-                                    app.currently_editing = true;
-                                }
-                                KeyCode::Char(QUIT_KEY) => {
-                                    app.current_screen = CurrentScreen::Exiting;
-                                }
-                                KeyCode::Char(HELP_KEY) => {
-                                    app.current_screen = CurrentScreen::Help;
-                                }
-                                KeyCode::Char(CONFIGURATION_KEY) => {
-                                    app.current_screen = CurrentScreen::Configuration;
-                                    app.editing_config_field = Some(EditingConfigField::ContributionGoal);
-                                }
-                                _ => {}
-                            },
-                            CurrentScreen::Exiting => match key.code {
-                                KeyCode::Char('y') | KeyCode::Char(QUIT_KEY) => {
-                                    return Ok(true); // Exit the app
-                                }
-                                KeyCode::Char('n') => {
-                                    app.current_screen = CurrentScreen::Main; // Return to main
-                                }
-                                _ => {}
-                            },
-                            CurrentScreen::Editing => {
-                                match key.kind {
-                                    KeyEventKind::Press => {
-                                        match key.code {
-                                            KeyCode::Up => {
-                                                if app.selected_index > 0 {
-                                                    app.selected_index -= 1;
-                                                }
-                                            }
-                                            KeyCode::Down => {
-                                                if app.selected_index < app.hosts.len() - 1 {
-                                                    app.selected_index += 1;
-                                                }
-                                            }
-                                            KeyCode::Enter => {
-                                                if app.currently_editing {
-                                                    app.save_new_host();
-                                                    if let Err(e) = save_to_host(app.hosts.clone()) {
-                                                        panic!("{}", e.to_string());
-                                                    }
-                                                    app.current_screen = CurrentScreen::Main;
-                                                }
-                                            }
-                                            KeyCode::Backspace => {
-                                                if app.currently_editing {
-                                                    app.host_input.pop();
-                                                }
-                                            }
-                                            KeyCode::Esc => {
-                                                app.current_screen = CurrentScreen::Main;
-                                                app.currently_editing = false;
-                                            }
-                                            KeyCode::Tab => {
-                                                if app.selected_index < app.hosts.len() {
-                                                    app.hosts.remove(app.selected_index);
-                                                    if app.selected_index >= app.hosts.len() && app.selected_index > 0 {
-                                                        app.selected_index -= 1;
-                                                    }
-                                                }
-                                            }
-                                            KeyCode::Char(value) => {
-                                                if app.currently_editing {
-                                                    app.host_input.push(value);
-                                                }
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
-                            CurrentScreen::Configuration => {
-                                match app.editing_config_field {
-                                    Some(EditingConfigField::ContributionGoal) => {
-                                        if let KeyCode::Char(c) = key.code {
-                                            app.contribution_goal_input.push(c);
-                                        } else if key.code == KeyCode::Backspace {
-                                            app.contribution_goal_input.pop();
-                                        }
-                                    }
-                                    Some(EditingConfigField::GithubUsername) => {
-                                        if let KeyCode::Char(c) = key.code {
-                                            app.github_username_input.push(c);
-                                        } else if key.code == KeyCode::Backspace {
-                                            app.github_username_input.pop();
-                                        }
-                                    }
-                                    None => {}  // Do nothing if no field is being edited
-                                }
-                                if key.code == KeyCode::Tab {
-                                    app.toggle_editing_config();
-                                }
-                                match key.kind {
-                                    KeyEventKind::Press => {
-                                        match key.code {
-                                            KeyCode::Esc => {
-                                                app.current_screen = CurrentScreen::Main;
-                                                app.editing_config_field = None;
-                                            }
-                                            KeyCode::Enter => {
-                                                if let Ok(new_goal) = app.contribution_goal_input.parse::<u32>() {
-                                                    app.contribution_goal = new_goal;
-                                                    app.username = app.github_username_input.clone();
-
-                                                    // Save the configuration back to the file
-                                                    save_config(CONFIG_FILE_PATH, &Config {
-                                                        github_username: app.username.clone(),
-                                                        contribution_goal: app.contribution_goal,
-                                                    }).expect("Failed to save config.");
-
-                                                    // Reset the editing field
-                                                    app.editing_config_field = None;
-
-                                                    // Return to the main screen
-                                                    app.current_screen = CurrentScreen::Main;
-                                                } else {
-                                                    app.contribution_goal_input = app.contribution_goal.to_string();
-                                                }
-                                            }
-                                            _ => {}
-                                        }
-                                    }
-                                    _ => {}
-                                }
-                            }
-                            CurrentScreen::Help => {
-                                match key.code {
-                                    _ => {
-                                        app.current_screen = CurrentScreen::Main
-                                    }
-                                }
-                            }
+    // Help can be opened from any screen, even while typing, except when a question needs answering first
+    let answering = app.current_screen == CurrentScreen::Exiting || app.pending_delete.is_some();
+    if key.code == HELP_KEY && key.kind == KeyEventKind::Press && app.current_screen != CurrentScreen::Help && !answering {
+        app.open_help();
+        return false;
+    }
+
+    match app.current_screen {
+        CurrentScreen::Main if app.filtering_hosts => match app.keys.action(Scope::Filter, &key) {
+            Some(Action::Save) => app.close_host_filter(true),
+            Some(Action::Cancel) => app.close_host_filter(false),
+            Some(_) => {}
+            None => match key.code {
+                KeyCode::Backspace => app.pop_host_filter_char(),
+                KeyCode::Char(value) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => app.push_host_filter_char(value),
+                _ => scroll_hosts(app, key.code),
+            },
+        },
+        CurrentScreen::Main => match app.keys.action(Scope::Main, &key) {
+            Some(Action::Insert) => {
+                app.open_host_editing();
+            }
+            Some(Action::Filter) => {
+                app.filtering_hosts = true;
+            }
+            Some(Action::Undo) if app.undo() => {
+                save_edits(app, hosts_file, paths);
+            }
+            Some(Action::Redo) if app.redo() => {
+                save_edits(app, hosts_file, paths);
+            }
+            Some(Action::Quit) => {
+                app.current_screen = CurrentScreen::Exiting;
+            }
+            Some(Action::Help) => {
+                app.open_help();
+            }
+            Some(Action::Configuration) => {
+                app.current_screen = CurrentScreen::Configuration;
+                app.editing_config_field = Some(EditingConfigField::ContributionGoal);
+            }
+            Some(Action::Refresh) => {
+                refresh.notify_one();
+            }
+            Some(Action::Bundles) => {
+                app.current_screen = CurrentScreen::Bundles;
+            }
+            Some(Action::History) => {
+                app.current_screen = CurrentScreen::History;
+                // The config file may have been edited into something unreadable since starting
+                if let Err(e) = load_history_screen(app, paths) {
+                    app.calendar_status = Some(e.to_string());
+                    return false;
+                }
+                app.calendar_status = Some(String::from("Fetching contributions from GitHub..."));
+                runtime.spawn(fetch_calendar(messages.clone(), paths.clone(), Arc::clone(token_store)));
+            }
+            Some(Action::Token) => {
+                app.current_screen = CurrentScreen::Token;
+                app.editing_token_field = Some(if token_store.has_secrets_file() { TokenField::Passphrase } else { TokenField::Token });
+            }
+            Some(_) => {}
+            None => scroll_hosts(app, key.code),
+        },
+        CurrentScreen::Exiting => {
+            if app.keys.matches(Action::Confirm, &key) || app.keys.matches(Action::Quit, &key) {
+                return true; // Exit the app
+            }
+            if app.keys.matches(Action::Cancel, &key) {
+                app.current_screen = CurrentScreen::Main; // Return to main
+            }
+        }
+        CurrentScreen::Editing if key.kind == KeyEventKind::Press => {
+            // Deleting a host needs confirming, as it's easy to do by accident
+            if let Some(index) = app.pending_delete.take() {
+                if app.keys.matches(Action::Confirm, &key) {
+                    app.delete_host(index);
+                }
+                return false;
+            }
+            match app.keys.action(Scope::Editing, &key) {
+                // Undoing here only affects the edits which haven't been saved yet
+                Some(Action::UndoUnsaved) if app.editing_host.is_none() => {
+                    app.undo();
+                }
+                Some(Action::Redo) if app.editing_host.is_none() => {
+                    app.redo();
+                }
+                Some(Action::EditHost) if app.editing_host.is_none() => {
+                    app.edit_selected_host();
+                }
+                Some(Action::DeleteHost) if app.editing_host.is_none() && app.selected_index < app.hosts.len() => {
+                    app.pending_delete = Some(app.selected_index);
+                }
+                // An invalid or duplicate host is left in the input to be corrected
+                Some(Action::Save) => {
+                    if app.save_new_host() {
+                        match update_hosts(hosts_file, &app.hosts, &app.bundles) {
+                            Ok(()) => app.hosts_error = None,
+                            Err(e) => app.report_error(&e),
                         }
+                        app.commit_host_editing();
                     }
-                    _ => {}
                 }
+                Some(Action::Cancel) => app.close_host_editing(),
+                Some(_) => {}
+                None => match key.code {
+                    KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown => scroll_hosts(app, key.code),
+                    KeyCode::Left => app.move_host_cursor(app.host_cursor.saturating_sub(1)),
+                    KeyCode::Right => app.move_host_cursor(app.host_cursor + 1),
+                    KeyCode::Home => app.move_host_cursor(0),
+                    KeyCode::End => app.move_host_cursor(usize::MAX),
+                    KeyCode::Backspace => app.delete_host_char_before_cursor(),
+                    KeyCode::Delete => app.delete_host_char_at_cursor(),
+                    KeyCode::Char(value) if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => app.insert_host_char(value),
+                    _ => {}
+                },
             }
         }
+        CurrentScreen::Editing => {}
+        CurrentScreen::Configuration => match app.keys.action(Scope::Configuration, &key) {
+            Some(Action::TestConnection) if app.connection_test.is_none() => {
+                start_connection_test(app, context, app.github_username_input.clone(), false);
+            }
+            Some(Action::NextField) => app.toggle_editing_config(),
+            Some(Action::Cancel) if key.kind == KeyEventKind::Press => {
+                app.current_screen = CurrentScreen::Main;
+                app.editing_config_field = None;
+                app.connection_status = None;
+                app.unverified_username = None;
+                app.connection_test = None;
+            }
+            Some(Action::Save) if key.kind == KeyEventKind::Press && app.connection_test.is_none() => {
+                // A changed username is checked with GitHub first, and saved once the check has finished
+                let username = app.github_username_input.clone();
+                if app.contribution_goal_input.parse::<u32>().is_ok() && username != app.username && app.unverified_username.as_ref() != Some(&username) {
+                    start_connection_test(app, context, username, true);
+                } else {
+                    save_configuration(app, paths);
+                }
+            }
+            Some(_) => {}
+            None => match app.editing_config_field {
+                Some(EditingConfigField::ContributionGoal) => {
+                    if let KeyCode::Char(c) = key.code {
+                        app.contribution_goal_input.push(c);
+                    } else if key.code == KeyCode::Backspace {
+                        app.contribution_goal_input.pop();
+                    }
+                }
+                Some(EditingConfigField::GithubUsername) => {
+                    if let KeyCode::Char(c) = key.code {
+                        app.github_username_input.push(c);
+                    } else if key.code == KeyCode::Backspace {
+                        app.github_username_input.pop();
+                    }
+                }
+                None => {}  // Do nothing if no field is being edited
+            },
+        },
+        CurrentScreen::Token if key.kind == KeyEventKind::Press => match app.keys.action(Scope::Token, &key) {
+            Some(Action::NextField) => app.toggle_editing_token(),
+            Some(Action::Cancel) => app.close_token_screen(),
+            Some(Action::Save) if !app.passphrase_input.is_empty() => {
+                // With a new token it's encrypted to the secrets file, otherwise the existing file is unlocked
+                let token = app.token_input.trim().to_string();
+                let result = if token.is_empty() {
+                    token_store.unlock(&app.passphrase_input)
+                } else {
+                    token_store.store(&token, &app.passphrase_input)
+                };
+                match result {
+                    Ok(()) => {
+                        let config = try_load_config(&paths.config_file).unwrap_or_default();
+                        app.token_status = Some(describe_token(token_store, config.token_file.as_deref()));
+                        app.close_token_screen();
+                    }
+                    Err(e) => app.token_status = Some(e.to_string()),
+                }
+            }
+            Some(_) => {}
+            None => match key.code {
+                KeyCode::Char(c) => match app.editing_token_field {
+                    Some(TokenField::Token) => app.token_input.push(c),
+                    Some(TokenField::Passphrase) => app.passphrase_input.push(c),
+                    None => {}
+                },
+                KeyCode::Backspace => match app.editing_token_field {
+                    Some(TokenField::Token) => { app.token_input.pop(); }
+                    Some(TokenField::Passphrase) => { app.passphrase_input.pop(); }
+                    None => {}
+                },
+                _ => {}
+            },
+        },
+        CurrentScreen::Token => {}
+        CurrentScreen::Bundles if key.kind == KeyEventKind::Press => match app.keys.action(Scope::Bundles, &key) {
+            Some(Action::Cancel | Action::Bundles) => app.current_screen = CurrentScreen::Main,
+            Some(Action::Help) => app.open_help(),
+            Some(_) => {}
+            None => match key.code {
+                KeyCode::Up => app.move_selection(-1),
+                KeyCode::Down => app.move_selection(1),
+                KeyCode::Char(' ') | KeyCode::Enter => {
+                    app.toggle_selected_bundle();
+                    // Saved straight away, keeping any settings which can't be edited here
+                    let mut config = try_load_config(&paths.config_file).unwrap_or_default();
+                    config.bundles = app.bundles.clone();
+                    if let Err(e) = save_config(&paths.config_file, &config) {
+                        app.report_error(&e);
+                    }
+                    match update_hosts(hosts_file, &app.hosts, &app.bundles) {
+                        Ok(()) => app.hosts_error = None,
+                        Err(e) => app.report_error(&e),
+                    }
+                }
+                _ => {}
+            },
+        },
+        CurrentScreen::Bundles => {}
+        CurrentScreen::History if key.kind == KeyEventKind::Press => match app.keys.action(Scope::History, &key) {
+            Some(Action::Cancel | Action::History) => app.current_screen = CurrentScreen::Main,
+            Some(Action::Help) => app.open_help(),
+            Some(Action::Quit) => app.current_screen = CurrentScreen::Exiting,
+            Some(_) | None => {}
+        },
+        CurrentScreen::History => {}
+        CurrentScreen::Help if key.kind == KeyEventKind::Press => match app.keys.action(Scope::Help, &key) {
+            Some(Action::Cancel | Action::Help) => app.close_help(),
+            Some(Action::NextField) => app.move_help_section(1),
+            Some(_) => {}
+            None => match key.code {
+                HELP_KEY => app.close_help(),
+                KeyCode::Left | KeyCode::BackTab => app.move_help_section(-1),
+                KeyCode::Right => app.move_help_section(1),
+                KeyCode::Up => app.scroll_help(-1),
+                KeyCode::Down => app.scroll_help(1),
+                KeyCode::PageUp => app.scroll_help(-(app.help_page_size as isize)),
+                KeyCode::PageDown => app.scroll_help(app.help_page_size as isize),
+                KeyCode::Home => app.help_scroll = 0,
+                KeyCode::End => app.scroll_help(isize::MAX),
+                _ => {}
+            },
+        },
+        CurrentScreen::Help => {}
     }
+    false
 }
 
-fn check_contribution_progress(configuration: &Config) -> u32 {
-    let client = reqwest::blocking::Client::new();
+/// Moves up and down the list of hosts with the arrow keys, or a whole page at a time
+fn scroll_hosts(app: &mut App, code: KeyCode) {
+    match code {
+        KeyCode::Up => app.move_selection(-1),
+        KeyCode::Down => app.move_selection(1),
+        KeyCode::PageUp => app.move_selection(-app.page_size()),
+        KeyCode::PageDown => app.move_selection(app.page_size()),
+        _ => {}
+    }
+}
 
-    let request_info = build_request_model(&configuration.github_username);
+/// Acts on a click or scroll, returning whether to exit. Clicking a key's hint in the footer is the same as pressing it.
+fn handle_mouse(app: &mut App, mouse: MouseEvent, area: Rect, context: &Context) -> bool {
+    let layout = ScreenLayout::new(area, app);
+    let position = Position::new(mouse.column, mouse.row);
+    // Clicks on a list's border don't select anything, and rows are counted from inside it
+    let inside = |area: Rect| area.inner(Margin::new(1, 1));
+    let row_in = |area: Rect| usize::from(mouse.row - inside(area).y);
+
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) if layout.key_footer.contains(position) => {
+            let column = usize::from(mouse.column.saturating_sub(layout.key_footer.x + 1));
+            if let Some(action) = footer_action_at(app, column) {
+                return handle_key(app, app.keys.chord(action).key_event(), context);
+            }
+        }
+        MouseEventKind::Down(MouseButton::Left) => match app.current_screen {
+            CurrentScreen::Main | CurrentScreen::Editing if inside(layout.hosts).contains(position) => {
+                app.select_host(row_in(layout.hosts));
+            }
+            CurrentScreen::Configuration if layout.popup_fields[0].contains(position) => {
+                app.editing_config_field = Some(EditingConfigField::ContributionGoal);
+            }
+            CurrentScreen::Configuration if layout.popup_fields[1].contains(position) => {
+                app.editing_config_field = Some(EditingConfigField::GithubUsername);
+            }
+            CurrentScreen::Token if layout.popup_fields[0].contains(position) => {
+                app.editing_token_field = Some(TokenField::Token);
+            }
+            CurrentScreen::Token if layout.popup_fields[1].contains(position) => {
+                app.editing_token_field = Some(TokenField::Passphrase);
+            }
+            CurrentScreen::Help if layout.help_tabs.contains(position) => {
+                if let Some(section) = help_section_at(usize::from(mouse.column - layout.help_tabs.x)) {
+                    app.show_help_section(section);
+                }
+            }
+            CurrentScreen::Bundles if inside(layout.bundles).contains(position) && row_in(layout.bundles) < BUNDLES.len() => {
+                app.selected_bundle = row_in(layout.bundles);
+            }
+            _ => {}
+        },
+        MouseEventKind::ScrollUp if app.current_screen == CurrentScreen::Help => app.scroll_help(-3),
+        MouseEventKind::ScrollDown if app.current_screen == CurrentScreen::Help => app.scroll_help(3),
+        // The wheel only moves through lists, rather than changing what's being typed into
+        MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+            if matches!(app.current_screen, CurrentScreen::Main | CurrentScreen::Editing | CurrentScreen::Bundles) =>
+        {
+            app.move_selection(if mouse.kind == MouseEventKind::ScrollUp { -1 } else { 1 });
+        }
+        _ => {}
+    }
+    false
+}
 
-    let response = client
-        .post(GH_API_PATH)
-        .header(header::USER_AGENT, "AppName/0.1")
-        .bearer_auth(&request_info.token)
-        .json(&request_info.body)
-        .send()
-        .unwrap().text().unwrap();
+/// Writes the hosts and configuration after an edit has been undone or redone
+fn save_edits(app: &mut App, hosts_file: &HostsFile, paths: &Paths) {
+    let mut config = try_load_config(&paths.config_file).unwrap_or_default();
+    config.github_username = app.username.clone();
+    config.contribution_goal = app.contribution_goal;
+    config.bundles = app.bundles.clone();
+    if let Err(e) = save_config(&paths.config_file, &config) {
+        app.report_error(&e);
+    }
+    match update_hosts(hosts_file, &app.hosts, &app.bundles) {
+        Ok(()) => app.hosts_error = None,
+        Err(e) => app.report_error(&e),
+    }
+}
 
-     find_contribution_count_today(response).unwrap()
+/// Loads what's shown on the history screen from the recorded history, other than the contributions from GitHub
+fn load_history_screen(app: &mut App, paths: &Paths) -> Result<(), CommitBlockError> {
+    let history = load_history(paths.history_file());
+    let contribution_day = try_load_config(&paths.config_file)?.contribution_day();
+    let today = contribution_day.today();
+    let start = contribution_day.bounds(today).0.with_timezone(&Local);
+
+    app.progress_timeline = history.progress_timeline(today, start, TimeDelta::minutes(PROGRESS_INTERVAL_MINUTES), Local::now());
+    app.progress_started_at = Some(start);
+    app.unlock_stats = Some(history.unlock_stats(&contribution_day));
+    app.goal_met_days = history.goal_met.into_keys().collect();
+    Ok(())
 }
 
-fn record_contribution_goal_met(date: NaiveDate, mut state: ContributionThresholdStatus, configuration: &Config) {
+fn record_contribution_goal_met(hosts_file: &HostsFile, paths: &Paths, date: NaiveDate, mut state: ContributionThresholdStatus, configuration: &Config) -> Result<(), CommitBlockError> {
     state.threshold_met_date = Some(date.format(DATE_FORMATTER).to_string());
     state.threshold_met_goal = Some(configuration.contribution_goal);
-    modify_hosts(UNBLOCK).expect("TODO: panic message");
-    persist_contribution_state(&state).expect("TODO: panic message");
+    modify_hosts(hosts_file, UNBLOCK).map_err(CommitBlockError::HostsFile)?;
+    persist_contribution_state(paths.state_file(), &state)?;
+
+    let mut history = load_history(paths.history_file());
+    if !history.goal_met.contains_key(&date) {
+        history.record_goal_met(date, configuration.contribution_goal, Local::now());
+        persist_history(paths.history_file(), &history)?;
+    }
+    Ok(())
 }
 
-fn load_contribution_state(file_path: &str) -> Option<ContributionThresholdStatus> {
+fn load_contribution_state(file_path: impl AsRef<Path>) -> Option<ContributionThresholdStatus> {
     let file = File::open(file_path).ok()?;
     let reader = BufReader::new(file);
     serde_json::from_reader(reader).ok()
 }
 
-fn persist_contribution_state(state: &ContributionThresholdStatus) -> io::Result<()> {
-    let file = OpenOptions::new().write(true).create(true).truncate(true).open(STATE_FILE_PATH)?;
-    serde_json::to_writer_pretty(file, state).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+fn load_contribution_cache(file_path: impl AsRef<Path>) -> Option<ContributionCache> {
+    let file = File::open(file_path).ok()?;
+    let reader = BufReader::new(file);
+    serde_json::from_reader(reader).ok()
 }
 
-fn save_config(file_path: &str, config: &Config) -> Result<(), io::Error> {
-    let toml_string = toml::to_string(config)
-        .expect("Failed to serialize config to TOML");
-
-    let mut file = File::create(file_path)?;
-    file.write_all(toml_string.as_bytes())?;
-
-    Ok(())
+fn persist_contribution_cache(file_path: impl AsRef<Path>, cache: &ContributionCache) -> io::Result<()> {
+    let file = OpenOptions::new().write(true).create(true).truncate(true).open(file_path)?;
+    serde_json::to_writer_pretty(file, cache).map_err(io::Error::other)
 }
 
-fn load_config(file_path: &str) -> Config {
-    let read_result = fs::read_to_string(file_path);
-    if read_result.is_ok() {
-        toml::from_str(&read_result.unwrap())
-            .expect("Failed to parse config file.")
-    } else {
-        Config {
-            github_username: "".to_string(),
-            contribution_goal: 1
-        }
-    }
+fn persist_contribution_state(file_path: impl AsRef<Path>, state: &ContributionThresholdStatus) -> io::Result<()> {
+    let file = OpenOptions::new().write(true).create(true).truncate(true).open(file_path)?;
+    serde_json::to_writer_pretty(file, state).map_err(io::Error::other)
 }
 
-fn build_request_model(username: &String) -> RequestModel {
-    dotenv().ok();
-    let token = env::var("GITHUB_TOKEN").expect("GITHUB_TOKEN not set");
-
-    let body = serde_json::json!({
-        "query": GRAPHQL_QUERY,
-        "variables": {
-            "userName": username
-        }
-    });
+fn save_config(file_path: impl AsRef<Path>, config: &Config) -> Result<(), CommitBlockError> {
+    let toml_string = toml::to_string(config)?;
 
-    RequestModel {
-        token,
-        body,
+    if let Some(parent) = file_path.as_ref().parent() {
+        fs::create_dir_all(parent)?;
     }
-}
 
-fn find_contribution_count_today(api_response: String) -> Result<u32, ()> {
-    let json_response: Value = serde_json::from_str(&api_response).unwrap();
+    let mut file = File::create(file_path)?;
+    file.write_all(toml_string.as_bytes())?;
 
-    let today = Utc::now().format(DATE_FORMATTER).to_string();
+    Ok(())
+}
 
-    if let Some(contributions) = json_response["data"]["user"]["contributionsCollection"]
-        ["contributionCalendar"]["weeks"]
-        .as_array()
-    {
-        for week in contributions {
-            if let Some(days) = week["contributionDays"].as_array() {
-                for day in days {
-                    if day["date"] == today {
-                        let contribution_count = day["contributionCount"]
-                            .as_i64()
-                            .unwrap_or(0); // Default to 0 if not found
-                        return Ok(contribution_count as u32);
-                    }
-                }
-            }
-        }
+/// Loads the configuration, falling back to the defaults if the file doesn't exist
+fn try_load_config(file_path: impl AsRef<Path>) -> Result<Config, CommitBlockError> {
+    if let Ok(contents) = fs::read_to_string(file_path) {
+        Ok(toml::from_str(&contents)?)
+    } else {
+        Ok(Config::default())
     }
-    Ok(0)  // Return 0 if no contribution for today
 }
 
-fn initialise_hosts() -> Vec<String> {
-    // TODO better handling
-    let hosts = File::open(HOST_FILE_PATH).unwrap();
-    let reader = BufReader::new(hosts);
+fn initialise_hosts(hosts_file: &HostsFile) -> Result<Vec<String>, CommitBlockError> {
+    let hosts_content = hosts_file.read().map_err(CommitBlockError::HostsFile)?;
+    Ok(parse_hosts(&hosts_content))
+}
 
+/// Reads the hosts in the CommitBlock section of the hosts file's contents
+fn parse_hosts(hosts_content: &str) -> Vec<String> {
     let mut inside_commit_block = false;
     let mut hosts: Vec<String> = Vec::new();
 
-    for line in reader.lines() {
-        let line = line.unwrap();
-
+    for line in hosts_content.lines() {
         if line == HOST_FILE_COMMIT_BLOCK_BEGIN {
             inside_commit_block = true;
             continue;
@@ -477,26 +1295,58 @@ fn initialise_hosts() -> Vec<String> {
         }
     }
 
-    hosts.dedup();
-    hosts
+    // Each host is written twice, once for IPv4 and once for IPv6, and may also have been added more than once
+    remove_duplicates(hosts)
 }
 
-fn get_trimmed_host_name(line: String, prefix_to_trim: &str) -> String {
-    let trimmed: String = line.strip_prefix(prefix_to_trim).unwrap_or(&line).parse().unwrap();
-    if trimmed.starts_with(HOST_FILE_LOCAL_PREFIX_IP6) {
-        return trimmed.strip_prefix(HOST_FILE_LOCAL_PREFIX_IP6).unwrap_or(&trimmed).parse().unwrap();
-    } else if trimmed.starts_with(HOST_FILE_LOCAL_PREFIX_DISABLED_IP6) {
-        return trimmed.strip_prefix(HOST_FILE_LOCAL_PREFIX_DISABLED_IP6).unwrap_or(&trimmed).parse().unwrap();
+/// Reads whether the hosts in the CommitBlock section of the hosts file are currently active or commented-out
+fn read_block_state(hosts_file: &HostsFile) -> Result<BlockState, CommitBlockError> {
+    let hosts_content = hosts_file.read().map_err(CommitBlockError::HostsFile)?;
+    let hosts = parse_hosts(&hosts_content);
+    Ok(BlockState::of(hosts_enforcement(&parse_host_states(&hosts_content), hosts.iter().map(String::as_str))))
+}
+
+/// Reads whether each host's IPv4 and IPv6 entries in the CommitBlock section of the hosts file are active or
+/// commented-out. Entries are split on whitespace rather than matched exactly, so that ones edited by hand are still read.
+fn parse_host_states(hosts_content: &str) -> BTreeMap<String, HostState> {
+    let mut host_states: BTreeMap<String, HostState> = BTreeMap::new();
+    let entries = hosts_content.lines()
+        .map(str::trim)
+        .skip_while(|line| *line != HOST_FILE_COMMIT_BLOCK_BEGIN)
+        .skip(1)
+        .take_while(|line| *line != HOST_FILE_COMMIT_BLOCK_END);
+    for line in entries {
+        let (active, entry) = match line.strip_prefix(HOST_FILE_BLOCK_PREFIX) {
+            Some(entry) => (false, entry),
+            None => (true, line),
+        };
+        let mut fields = entry.split_whitespace();
+        if let (Some(address), Some(host)) = (fields.next(), fields.next()) {
+            let state = host_states.entry(host.to_string()).or_default();
+            if address.contains(':') {
+                state.ipv6 = Some(active);
+            } else {
+                state.ipv4 = Some(active);
+            }
+        }
+    }
+    host_states
+}
+
+fn get_trimmed_host_name(line: &str, prefix_to_trim: &str) -> String {
+    let trimmed = line.strip_prefix(prefix_to_trim).unwrap_or(line);
+    if let Some(host) = trimmed.strip_prefix(HOST_FILE_LOCAL_PREFIX_IP6) {
+        host.to_string()
+    } else if let Some(host) = trimmed.strip_prefix(HOST_FILE_LOCAL_PREFIX_DISABLED_IP6) {
+        host.to_string()
     } else {
-        trimmed
+        trimmed.to_string()
     }
 }
 
 /// Saves the current host configuration to the host files
-fn save_to_host(hosts: Vec<String>) -> Result<(), io::Error> {
-    let mut hosts_file = File::open(HOST_FILE_PATH)?;
-    let mut hosts_content = String::new();
-    hosts_file.read_to_string(&mut hosts_content)?;
+fn save_to_host(hosts_file: &HostsFile, hosts: Vec<String>) -> Result<(), io::Error> {
+    let hosts_content = hosts_file.read()?;
 
     let before_block = hosts_content.lines()
         .take_while(|s| !s.starts_with(HOST_FILE_COMMIT_BLOCK_BEGIN));
@@ -507,35 +1357,41 @@ fn save_to_host(hosts: Vec<String>) -> Result<(), io::Error> {
     let mut new_hosts = String::new();
     for line in before_block.chain(after_block) {
         new_hosts.push_str(line);
-        new_hosts.push_str("\n")
+        new_hosts.push('\n')
     };
 
     new_hosts.push_str("### CommitBlock\n");
     for domain in hosts {
         new_hosts.push_str(HOST_FILE_LOCAL_PREFIX_IP4);
-        new_hosts.push_str(&*domain);
-        new_hosts.push_str("\n");
+        new_hosts.push_str(&domain);
+        new_hosts.push('\n');
         new_hosts.push_str(HOST_FILE_LOCAL_PREFIX_IP6);
-        new_hosts.push_str(&*domain);
-        new_hosts.push_str("\n");
+        new_hosts.push_str(&domain);
+        new_hosts.push('\n');
     };
     new_hosts.push_str("### End CommitBlock\n");
 
-    let mut file = File::create(HOST_FILE_PATH)?;
-    file.write_all(new_hosts.as_bytes())?;
+    hosts_file.write(&new_hosts)
+}
+
+/// Writes the hosts, along with those in the enabled bundles, to the hosts file. If the hosts were unblocked because
+/// today's goal has been met, they stay unblocked.
+fn update_hosts(hosts_file: &HostsFile, hosts: &[String], bundles: &[String]) -> Result<(), CommitBlockError> {
+    let block_state = read_block_state(hosts_file)?;
+    save_to_host(hosts_file, blocked_hosts(hosts, bundles)).map_err(CommitBlockError::HostsFile)?;
+    if block_state == BlockState::Unblocked {
+        modify_hosts(hosts_file, UNBLOCK).map_err(CommitBlockError::HostsFile)?;
+    }
     Ok(())
 }
 
-fn modify_hosts(toggle_option: HostToggleOption) -> Result<(), io::Error> {
-    let file = File::open(HOST_FILE_PATH)?;
-    let reader = BufReader::new(file);
+fn modify_hosts(hosts_file: &HostsFile, toggle_option: HostToggleOption) -> Result<(), io::Error> {
+    let hosts_content = hosts_file.read()?;
 
     let mut in_commitblock = false;
-    let mut output = Vec::new();
-
-    for line in reader.lines() {
-        let line = line?;
+    let mut output = String::new();
 
+    for line in hosts_content.lines() {
         if line.trim() == HOST_FILE_COMMIT_BLOCK_BEGIN {
             in_commitblock = true;
         } else if line.trim() == HOST_FILE_COMMIT_BLOCK_END {
@@ -546,52 +1402,44 @@ fn modify_hosts(toggle_option: HostToggleOption) -> Result<(), io::Error> {
             match toggle_option {
                 BLOCK => {
                     if line != HOST_FILE_COMMIT_BLOCK_BEGIN {
-                        output.push(line.strip_prefix(HOST_FILE_BLOCK_PREFIX).unwrap_or(&line).to_string())
+                        output.push_str(line.strip_prefix(HOST_FILE_BLOCK_PREFIX).unwrap_or(line))
                     } else {
-                        output.push(line.clone());
+                        output.push_str(line);
                     }
                 }
                 UNBLOCK => {
                     if !line.trim().starts_with(HOST_FILE_BLOCK_PREFIX) {
-                        output.push(format!("#{}", line));
+                        output.push_str(&format!("#{}", line));
                     } else {
-                        output.push(line.clone());
+                        output.push_str(line);
                     }
                 }
             }
         } else {
-            output.push(line.clone());
+            output.push_str(line);
         }
+        output.push('\n');
     }
 
-    let mut file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(HOST_FILE_PATH)?;
-
-    for line in output {
-        writeln!(file, "{}", line)?;
-    }
-
-    Ok(())
+    hosts_file.write(&output)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::help::HelpSection;
 
     #[test]
     fn load_config_file_not_found_return_default_config() {
-        let config = load_config("doesNotExist.toml");
+        let config = try_load_config("doesNotExist.toml").unwrap();
 
         assert_eq!(config.github_username, "".to_string());
         assert_eq!(config.contribution_goal, 1);
     }
 
     #[test]
-    #[should_panic(expected = "Failed to parse config file.")]
-    fn load_config_file_not_toml_panic() {
-        load_config(".gitignore");
+    fn load_config_file_not_toml_error() {
+        assert!(matches!(try_load_config(".gitignore"), Err(CommitBlockError::ConfigParse(_))));
     }
 
     #[test]
@@ -605,8 +1453,364 @@ mod tests {
     }
 
     #[test]
-    fn gh_api_path() {
-        assert_eq!(GH_API_PATH, "https://api.github.com/graphql")
+    fn load_config_without_poll_interval_uses_default() {
+        let config: Config = toml::from_str("github_username = \"BingBong\"\ncontribution_goal = 3").unwrap();
+
+        assert_eq!(config.poll_interval_secs, DEFAULT_POLL_INTERVAL_SECS);
+    }
+
+    #[test]
+    fn poll_interval_has_minimum() {
+        let config_with_interval = |secs: u64| -> Config {
+            toml::from_str(&format!("github_username = \"BingBong\"\ncontribution_goal = 3\npoll_interval_secs = {secs}")).unwrap()
+        };
+
+        assert_eq!(config_with_interval(0).poll_interval(), Duration::from_secs(MIN_POLL_INTERVAL_SECS));
+        assert_eq!(config_with_interval(MIN_POLL_INTERVAL_SECS - 1).poll_interval(), Duration::from_secs(MIN_POLL_INTERVAL_SECS));
+        assert_eq!(config_with_interval(MIN_POLL_INTERVAL_SECS * 5).poll_interval(), Duration::from_secs(MIN_POLL_INTERVAL_SECS * 5));
+    }
+
+    #[test]
+    fn load_config_with_keys() {
+        let config: Config = toml::from_str("github_username = \"BingBong\"\ncontribution_goal = 3\n[keys]\nquit = \"ctrl-q\"\ncancel = \"ctrl-c\"").unwrap();
+
+        let keys = KeyMap::new(&config.keys).unwrap();
+
+        assert_eq!(keys.chord(Action::Quit).to_string(), "Ctrl-q");
+        assert_eq!(keys.chord(Action::Cancel).to_string(), "Ctrl-c");
+        assert!(toml::from_str::<Config>("github_username = \"BingBong\"\ncontribution_goal = 3\n[keys]\nquit = \"hyper-q\"").is_err());
+    }
+
+    #[test]
+    fn load_config_offline_policy() {
+        let config: Config = toml::from_str("github_username = \"BingBong\"\ncontribution_goal = 3\noffline_policy = \"fail-closed\"").unwrap();
+        assert_eq!(config.offline_policy, OfflinePolicy::FailClosed);
+
+        let config: Config = toml::from_str("github_username = \"BingBong\"\ncontribution_goal = 3").unwrap();
+        assert_eq!(config.offline_policy, OfflinePolicy::StayUnblocked);
+    }
+
+    #[test]
+    fn load_config_timezone_and_day_start() {
+        let config: Config = toml::from_str("github_username = \"BingBong\"\ncontribution_goal = 3\ntimezone = \"Europe/London\"\nday_start = \"04:00\"").unwrap();
+
+        assert_eq!(config.timezone, Some(Tz::Europe__London));
+        assert_eq!(config.day_start, NaiveTime::from_hms_opt(4, 0, 0));
+        assert_eq!(config.contribution_day(), ContributionDay::new(Some(Tz::Europe__London), NaiveTime::from_hms_opt(4, 0, 0)));
+    }
+
+    #[test]
+    fn load_config_invalid_timezone() {
+        let result: Result<Config, _> = toml::from_str("github_username = \"BingBong\"\ncontribution_goal = 3\ntimezone = \"Mars/Olympus_Mons\"");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn poll_offline_uses_cached_progress() {
+        let paths = test_paths("poll-offline-cached");
+        let today = Local::now().date_naive();
+        persist_contribution_cache(paths.cache_file(), &ContributionCache {
+            date: today.format(DATE_FORMATTER).to_string(),
+            contribution_count: 3,
+            fetched_at: Local::now(),
+        }).unwrap();
+        let hosts_file = HostsFile::dry_run("doesNotExist");
+        save_to_host(&hosts_file, vec!(String::from("example.com"))).unwrap();
+        modify_hosts(&hosts_file, UNBLOCK).unwrap();
+
+        let outcome = poll_offline(&hosts_file, &paths, &Config::default(), today, true, CommitBlockError::Unauthorized).unwrap();
+
+        assert_eq!(outcome.contribution_count, 3);
+        assert!(outcome.goal_met);
+        assert!(matches!(outcome.fetch_error, Some(CommitBlockError::Unauthorized)));
+        assert_eq!(read_block_state(&hosts_file).unwrap(), BlockState::Unblocked);
+        fs::remove_dir_all(paths.state_dir).unwrap();
+    }
+
+    #[test]
+    fn poll_offline_ignores_previous_days_cache() {
+        let paths = test_paths("poll-offline-previous-day");
+        let today = Local::now().date_naive();
+        persist_contribution_cache(paths.cache_file(), &ContributionCache {
+            date: today.pred_opt().unwrap().format(DATE_FORMATTER).to_string(),
+            contribution_count: 3,
+            fetched_at: Local::now(),
+        }).unwrap();
+        let hosts_file = HostsFile::dry_run("doesNotExist");
+
+        let result = poll_offline(&hosts_file, &paths, &Config::default(), today, false, CommitBlockError::Unauthorized);
+
+        assert!(matches!(result, Err(CommitBlockError::Unauthorized)));
+        fs::remove_dir_all(paths.state_dir).unwrap();
+    }
+
+    #[test]
+    fn poll_offline_fail_closed_blocks_hosts() {
+        let paths = test_paths("poll-offline-fail-closed");
+        let hosts_file = HostsFile::dry_run("doesNotExist");
+        save_to_host(&hosts_file, vec!(String::from("example.com"))).unwrap();
+        modify_hosts(&hosts_file, UNBLOCK).unwrap();
+        let configuration = Config {
+            offline_policy: OfflinePolicy::FailClosed,
+            ..Config::default()
+        };
+
+        let result = poll_offline(&hosts_file, &paths, &configuration, Local::now().date_naive(), true, CommitBlockError::Unauthorized);
+
+        assert!(result.is_err());
+        assert_eq!(read_block_state(&hosts_file).unwrap(), BlockState::Blocked);
+        fs::remove_dir_all(paths.state_dir).unwrap();
+    }
+
+    #[test]
+    fn parse_host_states_reads_each_entry() {
+        let host_states = parse_host_states("127.0.0.1 localhost\n### CommitBlock\n127.0.0.1\texample.com\n#::1\t\texample.com\n#127.0.0.1   example.org\n\
+            # ::1 example.org\n0.0.0.0 example.net\n### End CommitBlock\n127.0.0.1 outside.com\n");
+
+        assert_eq!(host_states.len(), 3);
+        assert_eq!(host_states["example.com"], HostState { ipv4: Some(true), ipv6: Some(false) });
+        assert_eq!(host_states["example.org"].blocked(), Some(false));
+        assert_eq!(host_states["example.net"], HostState { ipv4: Some(true), ipv6: None });
+    }
+
+    fn test_paths(name: &str) -> Paths {
+        let state_dir = std::env::temp_dir().join(format!("commit-block-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&state_dir).unwrap();
+        Paths {
+            config_file: state_dir.join("config.toml"),
+            state_dir,
+            hosts_file: "doesNotExist".into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn watch_hosts_file_reports_changes_until_cancelled() {
+        let hosts_file = Arc::new(HostsFile::dry_run("doesNotExist"));
+        save_to_host(&hosts_file, vec!(String::from("example.com"))).unwrap();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let shutdown = CancellationToken::new();
+        let watcher = tokio::spawn(watch_hosts_file(tx, Arc::clone(&hosts_file), shutdown.clone()));
+
+        assert!(matches!(rx.recv().await, Some(PollerMessage::Hosts(hosts)) if hosts == vec!(String::from("example.com"))));
+        assert!(matches!(rx.recv().await, Some(PollerMessage::HostStates(states)) if states["example.com"].blocked() == Some(true)));
+        modify_hosts(&hosts_file, UNBLOCK).unwrap();
+        assert!(matches!(rx.recv().await, Some(PollerMessage::HostStates(states)) if states["example.com"].blocked() == Some(false)));
+        save_to_host(&hosts_file, vec!(String::from("example.com"), String::from("example.org"))).unwrap();
+        assert!(matches!(rx.recv().await, Some(PollerMessage::Hosts(hosts)) if hosts.len() == 2));
+        assert!(matches!(rx.recv().await, Some(PollerMessage::HostStates(states)) if states.len() == 2));
+
+        shutdown.cancel();
+        watcher.await.unwrap();
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[test]
+    fn next_poll_delay_goal_not_met() {
+        let poll_interval = Duration::from_secs(DEFAULT_POLL_INTERVAL_SECS);
+
+        let delay = next_poll_delay(poll_interval, false, None, Utc::now());
+
+        assert_eq!(delay, poll_interval);
+    }
+
+    #[test]
+    fn next_poll_delay_goal_met() {
+        let poll_interval = Duration::from_secs(DEFAULT_POLL_INTERVAL_SECS);
+
+        let delay = next_poll_delay(poll_interval, true, None, Utc::now());
+
+        assert_eq!(delay, poll_interval * GOAL_MET_POLL_INTERVAL_MULTIPLIER);
+    }
+
+    #[test]
+    fn next_poll_delay_rate_limit_low() {
+        let now = Utc::now();
+        // 100 requests left to spread over the next 200 minutes gives one every 2 minutes
+        let rate_limit = RateLimit { limit: 5000, remaining: 100, reset_at: now + chrono::Duration::minutes(200) };
+
+        let delay = next_poll_delay(Duration::from_secs(MIN_POLL_INTERVAL_SECS), false, Some(&rate_limit), now);
+
+        assert_eq!(delay, Duration::from_secs(120));
+    }
+
+    #[test]
+    fn next_poll_delay_rate_limit_exhausted() {
+        let now = Utc::now();
+        let rate_limit = RateLimit { limit: 5000, remaining: 0, reset_at: now + chrono::Duration::seconds(1000) };
+
+        let delay = next_poll_delay(Duration::from_secs(MIN_POLL_INTERVAL_SECS), false, Some(&rate_limit), now);
+
+        assert_eq!(delay, Duration::from_secs(1000));
+    }
+
+    #[test]
+    fn next_poll_delay_rate_limit_plentiful() {
+        let now = Utc::now();
+        let rate_limit = RateLimit { limit: 5000, remaining: 4000, reset_at: now + chrono::Duration::seconds(1000) };
+
+        let delay = next_poll_delay(Duration::from_secs(MIN_POLL_INTERVAL_SECS), false, Some(&rate_limit), now);
+
+        assert_eq!(delay, Duration::from_secs(MIN_POLL_INTERVAL_SECS));
+    }
+
+    #[test]
+    fn next_poll_delay_rate_limit_low_with_long_interval() {
+        let now = Utc::now();
+        let poll_interval = Duration::from_secs(MIN_POLL_INTERVAL_SECS * 5);
+        // Spreading the remaining requests out would poll more often than configured, so the interval wins
+        let rate_limit = RateLimit { limit: 5000, remaining: 100, reset_at: now + chrono::Duration::minutes(200) };
+
+        let delay = next_poll_delay(poll_interval, false, Some(&rate_limit), now);
+
+        assert_eq!(delay, poll_interval);
+    }
+
+    #[test]
+    fn read_block_state_from_hosts_file() {
+        let hosts_file = HostsFile::dry_run("doesNotExist");
+        assert_eq!(read_block_state(&hosts_file).unwrap(), BlockState::NotConfigured);
+
+        save_to_host(&hosts_file, vec!(String::from("example.com"))).unwrap();
+        assert_eq!(read_block_state(&hosts_file).unwrap(), BlockState::Blocked);
+
+        modify_hosts(&hosts_file, UNBLOCK).unwrap();
+        assert_eq!(read_block_state(&hosts_file).unwrap(), BlockState::Unblocked);
+
+        hosts_file.write("### CommitBlock\n127.0.0.1\texample.com\n#::1\t\texample.com\n### End CommitBlock\n").unwrap();
+        assert_eq!(read_block_state(&hosts_file).unwrap(), BlockState::PartiallyBlocked);
+    }
+
+    #[test]
+    fn update_hosts_includes_bundles_and_keeps_hosts_unblocked() {
+        let hosts_file = HostsFile::dry_run("doesNotExist");
+        save_to_host(&hosts_file, vec!(String::from("example.com"))).unwrap();
+        modify_hosts(&hosts_file, UNBLOCK).unwrap();
+        let bundles = vec!(String::from("video"));
+
+        update_hosts(&hosts_file, &[String::from("example.com")], &bundles).unwrap();
+
+        let hosts = initialise_hosts(&hosts_file).unwrap();
+        assert!(hosts.contains(&String::from("youtube.com")));
+        assert_eq!(custom_hosts(hosts, &bundles), vec!(String::from("example.com")));
+        assert_eq!(read_block_state(&hosts_file).unwrap(), BlockState::Unblocked);
+    }
+
+    fn configuring_username(username: &str) -> App {
+        let mut app = App::new(vec!(), 0, 1, String::from("BingBong"), None, None);
+        assert_eq!(app.connection_test, None);
+        assert_eq!(app.connection_status, None);
+        assert_eq!(app.unverified_username, None);
+        app.current_screen = CurrentScreen::Configuration;
+        app.editing_config_field = Some(EditingConfigField::GithubUsername);
+        app.github_username_input = String::from(username);
+        app.connection_test = Some(ConnectionTest { username: String::from(username), save: true });
+        app
+    }
+
+    fn report(user_exists: bool) -> Result<ConnectionReport, CommitBlockError> {
+        Ok(ConnectionReport { viewer_login: String::from("BingBong"), user_exists, scopes: None, token_expires_at: None })
+    }
+
+    #[test]
+    fn connection_test_saves_username_once_found() {
+        let paths = test_paths("connection-found");
+        let mut app = configuring_username("BongBing");
+
+        finish_connection_test(&mut app, &paths, String::from("BongBing"), report(true));
+
+        assert_eq!(app.connection_test, None);
+        assert_eq!(app.current_screen, CurrentScreen::Main);
+        assert_eq!(app.username, "BongBing");
+        assert_eq!(try_load_config(&paths.config_file).unwrap().github_username, "BongBing");
+        fs::remove_dir_all(paths.state_dir).unwrap();
+    }
+
+    #[test]
+    fn connection_test_keeps_popup_open_for_missing_user() {
+        let paths = test_paths("connection-missing");
+        let mut app = configuring_username("BongBing");
+
+        finish_connection_test(&mut app, &paths, String::from("BongBing"), report(false));
+
+        assert_eq!(app.current_screen, CurrentScreen::Configuration);
+        assert_eq!(app.unverified_username, Some(String::from("BongBing")));
+        assert_eq!(app.username, "BingBong");
+        assert!(!paths.config_file.exists());
+        fs::remove_dir_all(paths.state_dir).unwrap();
+    }
+
+    #[test]
+    fn connection_test_ignored_once_username_changed() {
+        let paths = test_paths("connection-changed");
+        let mut app = configuring_username("BongBing");
+        app.github_username_input = String::from("BongBin");
+
+        finish_connection_test(&mut app, &paths, String::from("BongBing"), report(true));
+
+        assert_eq!(app.connection_test, None);
+        assert_eq!(app.connection_status, None);
+        assert_eq!(app.current_screen, CurrentScreen::Configuration);
+        fs::remove_dir_all(paths.state_dir).unwrap();
+    }
+
+    fn test_context(name: &str) -> (Context, UnboundedReceiver<PollerMessage>) {
+        let paths = test_paths(name);
+        let (messages, rx) = mpsc::unbounded_channel();
+        let context = Context {
+            hosts_file: Arc::new(HostsFile::dry_run("doesNotExist")),
+            token_store: Arc::new(TokenStore::new(paths.secrets_file())),
+            paths,
+            runtime: Handle::current(),
+            refresh: Arc::new(Notify::new()),
+            messages,
+        };
+        (context, rx)
+    }
+
+    fn press(app: &mut App, context: &Context, code: KeyCode) -> bool {
+        handle_key(app, KeyEvent::new_with_kind(code, KeyModifiers::NONE, KeyEventKind::Press), context)
+    }
+
+    #[tokio::test]
+    async fn history_screen_keys() {
+        let (context, _rx) = test_context("history-keys");
+        let mut app = App::new(vec!(), 0, 1, String::from("BingBong"), None, None);
+
+        app.current_screen = CurrentScreen::History;
+        press(&mut app, &context, KeyCode::Char('h'));
+        assert_eq!(app.current_screen, CurrentScreen::Help);
+        press(&mut app, &context, KeyCode::Esc);
+        assert_eq!(app.current_screen, CurrentScreen::History);
+
+        press(&mut app, &context, KeyCode::Char('q'));
+        assert_eq!(app.current_screen, CurrentScreen::Exiting);
+
+        app.current_screen = CurrentScreen::History;
+        press(&mut app, &context, KeyCode::Char('i'));
+        assert_eq!(app.current_screen, CurrentScreen::History);
+        press(&mut app, &context, KeyCode::Esc);
+        assert_eq!(app.current_screen, CurrentScreen::Main);
+        fs::remove_dir_all(&context.paths.state_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn bundles_screen_keys() {
+        let (context, _rx) = test_context("bundles-keys");
+        let mut app = App::new(vec!(), 0, 1, String::from("BingBong"), None, None);
+
+        app.current_screen = CurrentScreen::Bundles;
+        press(&mut app, &context, KeyCode::Char('h'));
+        assert_eq!(app.current_screen, CurrentScreen::Help);
+        assert_eq!(app.help_section, HelpSection::BlockedHosts);
+        press(&mut app, &context, KeyCode::Esc);
+        assert_eq!(app.current_screen, CurrentScreen::Bundles);
+
+        press(&mut app, &context, KeyCode::Char(' '));
+        assert_eq!(app.bundles, vec!(String::from(BUNDLES[0].0)));
+        press(&mut app, &context, KeyCode::Char('b'));
+        assert_eq!(app.current_screen, CurrentScreen::Main);
+        fs::remove_dir_all(&context.paths.state_dir).unwrap();
     }
 
     #[test]
@@ -0,0 +1,190 @@
+use std::{env, fs, io};
+use std::path::{Path, PathBuf};
+
+use crate::cli::Args;
+use crate::error::CommitBlockError;
+
+const APP_DIR_NAME: &str = "commit-block";
+const CONFIG_FILE_NAME: &str = "config.toml";
+const STATE_FILE_NAME: &str = "state_file.json";
+const CACHE_FILE_NAME: &str = "contribution_cache.json";
+const HISTORY_FILE_NAME: &str = "history.json";
+const SECRETS_FILE_NAME: &str = "secrets.enc";
+const SOCKET_FILE_NAME: &str = "commit-block.sock";
+const ROOT_CONFIG_DIR: &str = "/etc";
+const ROOT_STATE_DIR: &str = "/var/lib";
+
+/// Locations of the files Commit Block reads and writes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Paths {
+    pub config_file: PathBuf,
+    pub state_dir: PathBuf,
+    pub hosts_file: PathBuf,
+}
+
+impl Paths {
+    /// Resolves the file locations, preferring command-line flags and environment variables over the defaults. The
+    /// defaults are only needed, and so only have to be found, for the locations which haven't been given.
+    pub fn resolve(args: &Args) -> Result<Paths, CommitBlockError> {
+        let default_dirs = || default_dirs(
+            is_root(),
+            env::var_os("XDG_CONFIG_HOME").map(PathBuf::from),
+            env::var_os("XDG_STATE_HOME").map(PathBuf::from),
+            env::var_os("HOME").map(PathBuf::from),
+        );
+
+        let config_file = match &args.config {
+            Some(config_file) => config_file.clone(),
+            None => default_dirs()?.0.join(CONFIG_FILE_NAME),
+        };
+        let state_dir = match &args.state_dir {
+            Some(state_dir) => state_dir.clone(),
+            None => default_dirs()?.1,
+        };
+
+        Ok(Paths { config_file, state_dir, hosts_file: args.hosts_file.clone() })
+    }
+
+    pub fn state_file(&self) -> PathBuf {
+        self.state_dir.join(STATE_FILE_NAME)
+    }
+
+    pub fn cache_file(&self) -> PathBuf {
+        self.state_dir.join(CACHE_FILE_NAME)
+    }
+
+    pub fn history_file(&self) -> PathBuf {
+        self.state_dir.join(HISTORY_FILE_NAME)
+    }
+
+    /// The encrypted secrets file is kept alongside the config file
+    pub fn secrets_file(&self) -> PathBuf {
+        self.config_file.with_file_name(SECRETS_FILE_NAME)
+    }
+
+    /// The socket on which the running application listens for commands
+    pub fn socket_file(&self) -> PathBuf {
+        self.state_dir.join(SOCKET_FILE_NAME)
+    }
+
+    /// Moves the config and state files from the locations used by earlier versions, which were relative to the
+    /// working directory. Files are only moved if nothing exists at the new location yet.
+    pub fn migrate_legacy_files(&self, legacy_config_file: &Path, legacy_state_file: &Path) -> io::Result<()> {
+        migrate_file(legacy_config_file, &self.config_file)?;
+        migrate_file(legacy_state_file, &self.state_file())
+    }
+}
+
+/// Whether the effective user is root, as it is when run with `sudo`. Unlike checking `/proc`, this works on every Unix.
+fn is_root() -> bool {
+    // SAFETY: geteuid takes no arguments, touches no memory and always succeeds
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Returns the default config and state directories. When running as root these are the system-wide locations,
+/// otherwise the XDG base directories are used, falling back to their defaults under the home directory.
+fn default_dirs(is_root: bool, xdg_config_home: Option<PathBuf>, xdg_state_home: Option<PathBuf>, home: Option<PathBuf>) -> Result<(PathBuf, PathBuf), CommitBlockError> {
+    if is_root {
+        return Ok((Path::new(ROOT_CONFIG_DIR).join(APP_DIR_NAME), Path::new(ROOT_STATE_DIR).join(APP_DIR_NAME)));
+    }
+
+    // The XDG spec says relative paths should be ignored. Without either, there's nowhere sensible to keep the files,
+    // as a relative path would depend on the directory Commit Block happened to be started from.
+    let xdg_dir = |xdg: Option<PathBuf>, fallback: &str| {
+        xdg.filter(|dir| dir.is_absolute())
+            .or_else(|| home.as_ref().filter(|home| home.is_absolute()).map(|home| home.join(fallback)))
+            .map(|dir| dir.join(APP_DIR_NAME))
+            .ok_or(CommitBlockError::NoHomeDirectory)
+    };
+
+    Ok((xdg_dir(xdg_config_home, ".config")?, xdg_dir(xdg_state_home, ".local/state")?))
+}
+
+fn migrate_file(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_file() || to.exists() {
+        return Ok(());
+    }
+
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Renaming fails when moving across file systems, so fall back to copying
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("commit-block-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn default_dirs_root() {
+        let (config_dir, state_dir) = default_dirs(true, Some(PathBuf::from("/xdg/config")), None, Some(PathBuf::from("/root"))).unwrap();
+
+        assert_eq!(config_dir, PathBuf::from("/etc/commit-block"));
+        assert_eq!(state_dir, PathBuf::from("/var/lib/commit-block"));
+    }
+
+    #[test]
+    fn default_dirs_xdg() {
+        let (config_dir, state_dir) = default_dirs(false, Some(PathBuf::from("/xdg/config")), Some(PathBuf::from("/xdg/state")), Some(PathBuf::from("/home/user"))).unwrap();
+
+        assert_eq!(config_dir, PathBuf::from("/xdg/config/commit-block"));
+        assert_eq!(state_dir, PathBuf::from("/xdg/state/commit-block"));
+    }
+
+    #[test]
+    fn default_dirs_home_fallback() {
+        let (config_dir, state_dir) = default_dirs(false, None, Some(PathBuf::from("relative")), Some(PathBuf::from("/home/user"))).unwrap();
+
+        assert_eq!(config_dir, PathBuf::from("/home/user/.config/commit-block"));
+        assert_eq!(state_dir, PathBuf::from("/home/user/.local/state/commit-block"));
+    }
+
+    #[test]
+    fn default_dirs_without_home_fail() {
+        let result = default_dirs(false, Some(PathBuf::from("/xdg/config")), None, None);
+
+        assert!(matches!(result, Err(CommitBlockError::NoHomeDirectory)));
+    }
+
+    #[test]
+    fn migrate_file_moves_legacy_file() {
+        let dir = temp_dir("migrate");
+        let from = dir.join("config.toml");
+        let to = dir.join("new").join("config.toml");
+        fs::write(&from, "contribution_goal = 3").unwrap();
+
+        migrate_file(&from, &to).unwrap();
+
+        assert!(!from.exists());
+        assert_eq!(fs::read_to_string(&to).unwrap(), "contribution_goal = 3");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn migrate_file_keeps_existing_file() {
+        let dir = temp_dir("migrate-existing");
+        let from = dir.join("old.toml");
+        let to = dir.join("new.toml");
+        fs::write(&from, "old").unwrap();
+        fs::write(&to, "new").unwrap();
+
+        migrate_file(&from, &to).unwrap();
+
+        assert_eq!(fs::read_to_string(&from).unwrap(), "old");
+        assert_eq!(fs::read_to_string(&to).unwrap(), "new");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
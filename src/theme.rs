@@ -0,0 +1,231 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+
+/// The built-in themes which can be chosen in the config file
+#[derive(Deserialize, Serialize, Clone, Copy, Default, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    /// For terminals with a dark background
+    #[default]
+    Dark,
+    /// For terminals with a light background
+    Light,
+    HighContrast,
+}
+
+/// The styles used to draw the interface, named for what they show rather than how they look
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub title: Style,
+    /// The name of each setting or status, shown before its value
+    pub label: Style,
+    pub value: Style,
+    /// Anything less important, such as hints and disabled entries
+    pub muted: Style,
+    pub text: Style,
+    /// The keys which can be pressed, shown at the bottom of the screen
+    pub key_hint: Style,
+    pub host: Style,
+    pub selected: Style,
+    /// Text being typed, such as a new host
+    pub input: Style,
+    /// The field which is being typed into, when there's more than one
+    pub focused: Style,
+    /// Something which is about to be deleted
+    pub danger: Style,
+    pub good: Style,
+    pub warning: Style,
+    pub bad: Style,
+    pub accent: Style,
+    pub heading: Style,
+    pub popup: Style,
+    /// The unfilled part of the progress bar
+    pub gauge: Style,
+    /// Days with no contributions, followed by increasing numbers of contributions
+    pub heatmap: [Style; 5],
+}
+
+impl Theme {
+    /// The chosen theme, unless colour has been turned off with `NO_COLOR`, in which case modifiers are used instead
+    pub fn new(name: ThemeName, no_color: bool) -> Theme {
+        if no_color {
+            return Theme::no_color();
+        }
+        match name {
+            ThemeName::Dark => Theme::dark(),
+            ThemeName::Light => Theme::light(),
+            ThemeName::HighContrast => Theme::high_contrast(),
+        }
+    }
+
+    fn dark() -> Theme {
+        Theme {
+            title: fg(Color::Green),
+            label: fg(Color::Yellow),
+            value: fg(Color::Green),
+            muted: fg(Color::DarkGray),
+            text: fg(Color::White),
+            key_hint: fg(Color::Red),
+            host: fg(Color::Yellow),
+            selected: fg(Color::Black).bg(Color::LightBlue),
+            input: fg(Color::Cyan),
+            focused: fg(Color::Green),
+            danger: fg(Color::White).bg(Color::Red),
+            good: fg(Color::Green),
+            warning: fg(Color::Yellow),
+            bad: fg(Color::Red),
+            accent: fg(Color::Cyan),
+            heading: fg(Color::White).add_modifier(Modifier::BOLD),
+            popup: Style::default().bg(Color::Black),
+            gauge: Style::default().bg(Color::Black),
+            heatmap: [
+                fg(Color::DarkGray),
+                fg(Color::Rgb(14, 68, 41)),
+                fg(Color::Rgb(0, 109, 50)),
+                fg(Color::Rgb(38, 166, 65)),
+                fg(Color::Rgb(57, 211, 83)),
+            ],
+        }
+    }
+
+    fn light() -> Theme {
+        Theme {
+            title: fg(Color::Blue).add_modifier(Modifier::BOLD),
+            label: fg(Color::Blue),
+            value: fg(Color::Black),
+            muted: fg(Color::Gray),
+            text: fg(Color::Black),
+            key_hint: fg(Color::Magenta),
+            host: fg(Color::Black),
+            selected: fg(Color::White).bg(Color::Blue),
+            input: fg(Color::Blue).add_modifier(Modifier::UNDERLINED),
+            focused: fg(Color::Blue).add_modifier(Modifier::BOLD),
+            danger: fg(Color::White).bg(Color::Red),
+            good: fg(Color::Green),
+            warning: fg(Color::Rgb(175, 95, 0)),
+            bad: fg(Color::Red),
+            accent: fg(Color::Magenta),
+            heading: fg(Color::Black).add_modifier(Modifier::BOLD),
+            popup: Style::default().bg(Color::White),
+            gauge: Style::default().bg(Color::Gray),
+            heatmap: [
+                fg(Color::Rgb(235, 237, 240)),
+                fg(Color::Rgb(155, 233, 168)),
+                fg(Color::Rgb(64, 196, 99)),
+                fg(Color::Rgb(48, 161, 78)),
+                fg(Color::Rgb(33, 110, 57)),
+            ],
+        }
+    }
+
+    fn high_contrast() -> Theme {
+        let bold = |colour| fg(colour).add_modifier(Modifier::BOLD);
+        Theme {
+            title: bold(Color::White),
+            label: bold(Color::Yellow),
+            value: bold(Color::White),
+            muted: fg(Color::Gray),
+            text: fg(Color::White),
+            key_hint: bold(Color::Yellow),
+            host: fg(Color::White),
+            selected: bold(Color::Black).bg(Color::Yellow),
+            input: bold(Color::Cyan).add_modifier(Modifier::UNDERLINED),
+            focused: bold(Color::Yellow),
+            danger: bold(Color::White).bg(Color::Red),
+            good: bold(Color::LightGreen),
+            warning: bold(Color::LightYellow),
+            bad: bold(Color::LightRed),
+            accent: bold(Color::LightCyan),
+            heading: bold(Color::White).add_modifier(Modifier::UNDERLINED),
+            popup: Style::default().bg(Color::Black),
+            gauge: Style::default().bg(Color::Black),
+            heatmap: [
+                fg(Color::Gray),
+                fg(Color::Green),
+                fg(Color::LightGreen),
+                bold(Color::LightGreen),
+                bold(Color::White),
+            ],
+        }
+    }
+
+    /// Marks state with bold, underlined and reversed text, as colours can't be relied on
+    fn no_color() -> Theme {
+        let modifier = |modifier| Style::default().add_modifier(modifier);
+        Theme {
+            title: modifier(Modifier::BOLD),
+            label: Style::default(),
+            value: modifier(Modifier::BOLD),
+            muted: modifier(Modifier::DIM),
+            text: Style::default(),
+            key_hint: Style::default(),
+            host: Style::default(),
+            selected: modifier(Modifier::REVERSED),
+            input: modifier(Modifier::UNDERLINED),
+            focused: modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            danger: modifier(Modifier::REVERSED | Modifier::BOLD),
+            good: modifier(Modifier::BOLD),
+            warning: modifier(Modifier::BOLD | Modifier::ITALIC),
+            bad: modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            accent: modifier(Modifier::ITALIC),
+            heading: modifier(Modifier::BOLD),
+            popup: Style::default(),
+            gauge: Style::default(),
+            heatmap: [
+                modifier(Modifier::DIM),
+                Style::default(),
+                Style::default(),
+                modifier(Modifier::BOLD),
+                modifier(Modifier::BOLD),
+            ],
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+
+fn fg(colour: Color) -> Style {
+    Style::default().fg(colour)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_color_overrides_chosen_theme() {
+        let theme = Theme::new(ThemeName::Light, true);
+
+        assert_eq!(theme, Theme::no_color());
+    }
+
+    #[test]
+    fn no_color_theme_has_no_colours() {
+        let theme = Theme::no_color();
+        let styles = [theme.title, theme.label, theme.value, theme.muted, theme.text, theme.key_hint, theme.host, theme.selected,
+            theme.input, theme.focused, theme.danger, theme.good, theme.warning, theme.bad, theme.accent, theme.heading, theme.popup, theme.gauge];
+
+        for style in styles.iter().chain(theme.heatmap.iter()) {
+            assert_eq!((style.fg, style.bg), (None, None));
+        }
+        assert_ne!(theme.selected, theme.host);
+        assert_ne!(theme.danger, theme.host);
+    }
+
+    #[test]
+    fn theme_name_from_config() {
+        #[derive(Deserialize)]
+        struct Config {
+            theme: ThemeName,
+        }
+
+        let config: Config = toml::from_str("theme = \"high-contrast\"").unwrap();
+
+        assert_eq!(config.theme, ThemeName::HighContrast);
+        assert_eq!(Theme::new(config.theme, false), Theme::high_contrast());
+    }
+}
@@ -0,0 +1,279 @@
+use std::{env, fmt, fs};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use argon2::Argon2;
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use chacha20poly1305::aead::{Aead, AeadCore, OsRng};
+use chacha20poly1305::aead::rand_core::RngCore;
+
+use crate::error::CommitBlockError;
+
+const TOKEN_ENV_VAR: &str = "GITHUB_TOKEN";
+const GH_CONFIG_DIR_ENV_VAR: &str = "GH_CONFIG_DIR";
+const GH_HOSTS_FILE_NAME: &str = "hosts.yml";
+const GH_HOST: &str = "github.com";
+const GH_TOKEN_KEY: &str = "oauth_token";
+const SECRETS_FILE_MAGIC: &[u8] = b"CBSECRET1";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+/// Permission bits which give anyone other than a file's owner access to it
+const GROUP_OR_OTHER_ACCESS: u32 = 0o077;
+
+/// Where the GitHub token in use was found
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenSource {
+    Environment,
+    TokenFile,
+    GhCli,
+    SecretsFile,
+}
+
+impl fmt::Display for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenSource::Environment => write!(f, "{} environment variable", TOKEN_ENV_VAR),
+            TokenSource::TokenFile => write!(f, "token file"),
+            TokenSource::GhCli => write!(f, "GitHub CLI"),
+            TokenSource::SecretsFile => write!(f, "encrypted secrets file"),
+        }
+    }
+}
+
+/// Looks up the GitHub token from each of the supported sources in turn. The encrypted secrets file is only used once
+/// it has been unlocked with its passphrase, after which the decrypted token is kept in memory.
+pub struct TokenStore {
+    secrets_file: PathBuf,
+    unlocked_token: Mutex<Option<String>>,
+}
+
+impl TokenStore {
+    pub fn new(secrets_file: impl Into<PathBuf>) -> TokenStore {
+        TokenStore {
+            secrets_file: secrets_file.into(),
+            unlocked_token: Mutex::new(None),
+        }
+    }
+
+    /// Finds the token to use, in order of priority: the environment variable, the configured token file, the GitHub
+    /// CLI's config, and finally the encrypted secrets file
+    pub fn resolve(&self, token_file: Option<&Path>) -> Result<(String, TokenSource), CommitBlockError> {
+        if let Some(token) = env::var(TOKEN_ENV_VAR).ok().filter(|token| !token.is_empty()) {
+            return Ok((token, TokenSource::Environment));
+        }
+        if let Some(token_file) = token_file {
+            return Ok((read_token_file(token_file)?, TokenSource::TokenFile));
+        }
+        if let Some(token) = gh_hosts_file().and_then(|hosts_file| fs::read_to_string(hosts_file).ok())
+            .and_then(|hosts| find_gh_token(&hosts)) {
+            return Ok((token, TokenSource::GhCli));
+        }
+        if let Some(token) = self.unlocked_token.lock().unwrap().clone() {
+            return Ok((token, TokenSource::SecretsFile));
+        }
+        Err(CommitBlockError::MissingToken)
+    }
+
+    pub fn has_secrets_file(&self) -> bool {
+        self.secrets_file.is_file()
+    }
+
+    /// Decrypts the secrets file, keeping the token in memory for subsequent lookups
+    pub fn unlock(&self, passphrase: &str) -> Result<(), CommitBlockError> {
+        let contents = fs::read(&self.secrets_file)?;
+        let token = decrypt(&contents, passphrase)?;
+        *self.unlocked_token.lock().unwrap() = Some(token);
+        Ok(())
+    }
+
+    /// Encrypts the token to the secrets file, and starts using it straight away
+    pub fn store(&self, token: &str, passphrase: &str) -> Result<(), CommitBlockError> {
+        let contents = encrypt(token, passphrase)?;
+        if let Some(parent) = self.secrets_file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&self.secrets_file)?;
+        // The mode is only applied when the file is created, so make sure an existing file is private too
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(&contents)?;
+
+        *self.unlocked_token.lock().unwrap() = Some(token.to_string());
+        Ok(())
+    }
+}
+
+fn read_token_file(token_file: &Path) -> Result<String, CommitBlockError> {
+    let permissions = fs::metadata(token_file)?.permissions();
+    if permissions.mode() & GROUP_OR_OTHER_ACCESS != 0 {
+        return Err(CommitBlockError::InsecureTokenFile(token_file.to_path_buf()));
+    }
+
+    let token = fs::read_to_string(token_file)?.trim().to_string();
+    if token.is_empty() {
+        return Err(CommitBlockError::MissingToken);
+    }
+    Ok(token)
+}
+
+fn gh_hosts_file() -> Option<PathBuf> {
+    let config_dir = env::var_os(GH_CONFIG_DIR_ENV_VAR).map(PathBuf::from)
+        .or_else(|| env::var_os("XDG_CONFIG_HOME").map(|dir| PathBuf::from(dir).join("gh")))
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("gh")))?;
+    Some(config_dir.join(GH_HOSTS_FILE_NAME))
+}
+
+/// Finds the token for github.com in the GitHub CLI's `hosts.yml`. Only the simple subset of YAML which the CLI writes
+/// is understood. Newer versions of the CLI keep the token in the system keyring instead, in which case there's no
+/// token to find.
+fn find_gh_token(hosts: &str) -> Option<String> {
+    let mut in_github_host = false;
+
+    for line in hosts.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            in_github_host = line.trim_end().trim_end_matches(':').trim_matches('"') == GH_HOST;
+            continue;
+        }
+
+        if in_github_host {
+            if let Some((key, value)) = line.trim().split_once(':') {
+                let value = value.trim().trim_matches('"').trim_matches('\'');
+                if key == GH_TOKEN_KEY && !value.is_empty() {
+                    return Some(value.to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, CommitBlockError> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| CommitBlockError::Secrets(e.to_string()))?;
+    Ok(key)
+}
+
+/// Encrypts the token with a key derived from the passphrase. The output is laid out as the magic bytes, followed by
+/// the salt, the nonce and finally the ciphertext.
+fn encrypt(token: &str, passphrase: &str) -> Result<Vec<u8>, CommitBlockError> {
+    let mut salt = [0u8; SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let ciphertext = cipher.encrypt(&nonce, token.as_bytes())
+        .map_err(|_| CommitBlockError::Secrets("Failed to encrypt token".to_string()))?;
+
+    Ok([SECRETS_FILE_MAGIC, &salt, &nonce, &ciphertext].concat())
+}
+
+fn decrypt(contents: &[u8], passphrase: &str) -> Result<String, CommitBlockError> {
+    let invalid_file = || CommitBlockError::Secrets("Secrets file is corrupt".to_string());
+
+    let contents = contents.strip_prefix(SECRETS_FILE_MAGIC).ok_or_else(invalid_file)?;
+    if contents.len() < SALT_LENGTH + NONCE_LENGTH {
+        return Err(invalid_file());
+    }
+    let (salt, contents) = contents.split_at(SALT_LENGTH);
+    let (nonce, ciphertext) = contents.split_at(NONCE_LENGTH);
+
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt)?);
+    let plaintext = cipher.decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| CommitBlockError::Secrets("Incorrect passphrase".to_string()))?;
+
+    String::from_utf8(plaintext).map_err(|_| invalid_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("commit-block-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn can_encrypt_and_decrypt_token() {
+        let encrypted = encrypt("ghp_secret", "correct horse").unwrap();
+
+        assert!(!encrypted.windows(10).any(|window| window == b"ghp_secret"));
+        assert_eq!(decrypt(&encrypted, "correct horse").unwrap(), "ghp_secret");
+    }
+
+    #[test]
+    fn decrypt_wrong_passphrase() {
+        let encrypted = encrypt("ghp_secret", "correct horse").unwrap();
+
+        let result = decrypt(&encrypted, "battery staple");
+
+        assert_eq!(result.unwrap_err().to_string(), "Failed to read secrets: Incorrect passphrase");
+    }
+
+    #[test]
+    fn decrypt_corrupt_file() {
+        let result = decrypt(b"not a secrets file", "correct horse");
+
+        assert_eq!(result.unwrap_err().to_string(), "Failed to read secrets: Secrets file is corrupt");
+    }
+
+    #[test]
+    fn can_find_gh_token() {
+        let hosts = "github.com:\n    user: BingBong\n    oauth_token: gho_token\n    git_protocol: https\n";
+
+        assert_eq!(find_gh_token(hosts), Some(String::from("gho_token")));
+    }
+
+    #[test]
+    fn find_gh_token_ignores_other_hosts() {
+        let hosts = "github.example.com:\n    oauth_token: gho_enterprise\ngithub.com:\n    user: BingBong\n";
+
+        assert_eq!(find_gh_token(hosts), None);
+    }
+
+    #[test]
+    fn read_token_file_refuses_world_readable_file() {
+        let token_file = temp_file("world-readable-token");
+        fs::write(&token_file, "ghp_secret\n").unwrap();
+
+        for mode in [0o644, 0o640, 0o604] {
+            fs::set_permissions(&token_file, fs::Permissions::from_mode(mode)).unwrap();
+
+            let result = read_token_file(&token_file);
+
+            assert!(matches!(result, Err(CommitBlockError::InsecureTokenFile(_))), "mode {:o} was accepted", mode);
+        }
+        fs::remove_file(token_file).unwrap();
+    }
+
+    #[test]
+    fn read_token_file_trims_token() {
+        let token_file = temp_file("private-token");
+        fs::write(&token_file, "ghp_secret\n").unwrap();
+        fs::set_permissions(&token_file, fs::Permissions::from_mode(0o600)).unwrap();
+
+        assert_eq!(read_token_file(&token_file).unwrap(), "ghp_secret");
+        fs::remove_file(token_file).unwrap();
+    }
+
+    #[test]
+    fn can_store_and_unlock_secrets_file() {
+        let secrets_file = temp_file("secrets");
+        TokenStore::new(&secrets_file).store("ghp_secret", "correct horse").unwrap();
+
+        let token_store = TokenStore::new(&secrets_file);
+        token_store.unlock("correct horse").unwrap();
+
+        assert_eq!(*token_store.unlocked_token.lock().unwrap(), Some(String::from("ghp_secret")));
+        assert_eq!(fs::metadata(&secrets_file).unwrap().permissions().mode() & 0o777, 0o600);
+        fs::remove_file(secrets_file).unwrap();
+    }
+}
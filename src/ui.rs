@@ -1,114 +1,268 @@
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, Local, NaiveDate, TimeDelta};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::Style,
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Sparkline, Tabs, Wrap},
     Frame,
 };
 use ratatui::style::{Modifier, Stylize};
 use ratatui::widgets::Gauge;
-use crate::app::{App, CurrentScreen, EditingConfigField};
+use crate::app::{App, BlockState, CurrentScreen, EditingConfigField, Enforcement, StatusMessage, TokenField};
 use crate::app::EditingConfigField::{ContributionGoal, GithubUsername};
+use crate::bundles::{bundle_hosts, BUNDLES};
+use crate::github::CalendarDay;
+use crate::help::{self, HelpSection};
+use crate::history::UnlockStats;
+use crate::keys::Action;
+use crate::theme::Theme;
+
+const HEATMAP_DAY: &str = "■";
+/// Days on which the goal was met and the hosts were unlocked
+const HEATMAP_GOAL_MET_DAY: &str = "◆";
+/// Space for the weekday labels to the left of the heatmap
+const HEATMAP_LABEL_WIDTH: usize = 4;
+/// Shown before each host to say whether it's blocked in the hosts file, followed by a space. Each is two columns wide
+const BLOCKED_ICON: &str = "🔒 ";
+const UNBLOCKED_ICON: &str = "🔓 ";
+const INCONSISTENT_ICON: &str = "❗ ";
+const NO_ICON: &str = "   ";
+const ICON_WIDTH: u16 = 3;
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// Where each part of the interface is drawn, so that mouse clicks can be matched up with what was clicked on
+pub struct ScreenLayout {
+    pub title: Rect,
+    pub body: Rect,
+    pub hosts: Rect,
+    pub configuration: Rect,
+    pub status: Rect,
+    /// The hosts file diff, shown in dry-run mode
+    pub diff: Option<Rect>,
+    pub mode_footer: Rect,
+    pub key_footer: Rect,
+    pub progress: Rect,
+    /// The two fields of the configuration and token popups, followed by the status shown beneath them
+    pub popup_fields: [Rect; 3],
+    pub bundles: Rect,
+    /// The help screen, which covers everything but the footer so that its keys stay visible
+    pub help: Rect,
+    pub help_tabs: Rect,
+    pub help_body: Rect,
+}
 
-const HELP_SECTION_TEXT: &str = r#"
-            Commit Blocker allows you to block a configured list of hosts until a given GitHub contribution goal has been met for the day.
+impl ScreenLayout {
+    pub fn new(area: Rect, app: &App) -> ScreenLayout {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(1),
+                Constraint::Length(3),
+            ])
+            .split(area);
+
+        // In dry-run mode, the bottom of the middle section is given over to the hosts file diff
+        let body_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(if app.dry_run {
+                vec![Constraint::Percentage(60), Constraint::Percentage(40)]
+            } else {
+                vec![Constraint::Percentage(100)]
+            })
+            .split(chunks[1]);
 
-            `Blocked Hosts`
-            The `Blocked Hosts` panel shows the currently configured list of hosts which will be blocked until today's contribution goal is met.
-            Pressing (i) will enter Insert mode, where you can add new entries to the list. Pressing (tab) will delete the currently highlighted
-            host. Press (esc) to quit Insert mode without saving changes, and (enter) to save and exit.
+        let middle_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(body_chunks[0]);
 
-            `Configuration`
-            This panel displays the current configuration, including the current contribution target and today's current contribution count.
-            Press (c) to enter edit mode, where you can adjust the configuration to alter the contribution goal and update the GitHub username.
-            Pressing (tab) will toggle between the two configuration panels. Press (esc) to exit without saving, or press (enter) to save your changes.
+        let right_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(10)])
+            .split(middle_chunks[1]);
 
-            When the contribution goal has been met, the `/etc/hosts` file will be updated to comment-out the list of hosts. This will reset the following day,
-            and the contribution goal will need to be met again in order to unblock the hosts.
-            "#;
+        let footer_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(33), Constraint::Percentage(33), Constraint::Percentage(34)])
+            .split(chunks[2]);
 
-pub fn ui(frame: &mut Frame, app: &App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(1),
-            Constraint::Length(3),
-        ])
-        .split(frame.area());
+        let help = chunks[0].union(chunks[1]);
+        let help_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([Constraint::Length(1), Constraint::Length(1), Constraint::Min(0)])
+            .split(help);
 
-    let middle_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(chunks[1]);
+        let popup_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Length(3), Constraint::Length(4), Constraint::Min(0)])
+            .split(area);
+
+        ScreenLayout {
+            title: chunks[0],
+            body: chunks[1],
+            hosts: middle_chunks[0],
+            configuration: right_chunks[0],
+            status: right_chunks[1],
+            diff: body_chunks.get(1).copied(),
+            mode_footer: footer_chunks[0],
+            key_footer: footer_chunks[1],
+            progress: footer_chunks[2],
+            popup_fields: [popup_chunks[1], popup_chunks[2], popup_chunks[3]],
+            bundles: centered_rect(50, 50, area),
+            help,
+            help_tabs: help_chunks[0],
+            help_body: help_chunks[2],
+        }
+    }
+}
+
+pub fn ui(frame: &mut Frame, app: &mut App) {
+    let theme = &app.theme;
+    let layout = ScreenLayout::new(frame.area(), app);
 
     let title_block = Block::default()
         .borders(Borders::ALL)
         .style(Style::default());
 
-    let title = Paragraph::new(Text::styled(
-        "Commit Blocker",
-        Style::default().fg(Color::Green),
-    ))
+    let mut title_spans = vec![Span::styled("Commit Blocker", theme.title)];
+    if app.dry_run {
+        title_spans.push(Span::raw(" "));
+        title_spans.push(Span::styled("[DRY RUN]", theme.warning.add_modifier(Modifier::BOLD)));
+    }
+    // What the hosts file actually says, which may not be what's expected from the progress
+    if let Some(enforcement) = app.enforcement() {
+        let (badge, style) = match enforcement {
+            Enforcement::Enforced => ("[ENFORCED]", theme.bad),
+            Enforcement::Relaxed => ("[RELAXED]", theme.good),
+            Enforcement::Inconsistent => ("[INCONSISTENT]", theme.danger),
+        };
+        title_spans.push(Span::raw(" "));
+        title_spans.push(Span::styled(badge, style.add_modifier(Modifier::BOLD)));
+    }
+
+    let title = Paragraph::new(Line::from(title_spans))
         .block(title_block);
 
-    frame.render_widget(title, chunks[0]);
+    frame.render_widget(title, layout.title);
 
+    let visible_hosts = app.visible_hosts();
     let mut list_items = Vec::<ListItem>::new();
 
-    for (index, host) in app.hosts.iter().enumerate() {
-        let style = if app.currently_editing && index == app.selected_index {
-            Style::default().fg(Color::Green).bg(Color::LightBlue)
+    for &index in &visible_hosts {
+        let host = &app.hosts[index];
+        // A host being edited is replaced by the input, so that it's corrected in place
+        let (host, style) = if app.editing_host == Some(index) {
+            (&app.host_input, theme.input)
+        } else if app.pending_delete == Some(index) {
+            (host, theme.danger)
+        } else if app.currently_editing && index == app.selected_index {
+            (host, theme.selected)
         } else {
-            Style::default().fg(Color::Yellow)
+            (host, theme.host)
         };
-        list_items.push(ListItem::new(Line::from(Span::styled(
-            format!("{: <25}", host),
-            style,
-        ))));
+        list_items.push(ListItem::new(Line::from_iter([
+            enforcement_icon(app, [app.hosts[index].as_str()]),
+            Span::styled(format!("{: <25}", host), style),
+        ])));
     }
 
     // Include the new host input line in edit mode
     if app.currently_editing {
-        list_items.push(ListItem::new(Line::from(Span::styled(
-            format!("{: <25}", app.host_input),
-            Style::default().fg(Color::Cyan),
-        ))));
+        if app.editing_host.is_none() {
+            list_items.push(ListItem::new(Line::from_iter([
+                Span::raw(NO_ICON),
+                Span::styled(format!("{: <25}", app.host_input), theme.input),
+            ])));
+        }
+        if let Some(host_error) = &app.host_error {
+            list_items.push(ListItem::new(Line::from_iter([
+                Span::raw(NO_ICON),
+                Span::styled(format!("! {}", host_error), theme.bad),
+            ])));
+        }
     }
 
+    // Bundles are listed after the individual hosts, as their hosts can't be edited one by one
+    for name in &app.bundles {
+        list_items.push(ListItem::new(Line::from_iter([
+            enforcement_icon(app, bundle_hosts(name).iter().copied()),
+            Span::styled(format!("[{} bundle]", name), theme.muted),
+        ])));
+    }
+
+    let hosts_title = match (app.host_filter.is_empty(), app.filtering_hosts) {
+        (true, false) => format!("Blocked hosts ({})", app.hosts.len()),
+        _ => format!("Blocked hosts ({} of {}) /{}", visible_hosts.len(), app.hosts.len(), app.host_filter),
+    };
+
     let left_block = List::new(
         list_items
     ).block(Block::default()
         .borders(Borders::ALL)
-        .title("Blocked hosts"));
+        .title(hosts_title.as_str()));
 
-    frame.render_widget(left_block, middle_chunks[0]);
+    // The row being typed into is kept in view, or the highlighted host if nothing's being typed. Nothing is selected
+    // on the main screen, so that the list can be scrolled freely
+    let input_row = match app.editing_host {
+        Some(index) => visible_hosts.iter().position(|visible| *visible == index),
+        None => Some(visible_hosts.len()),
+    };
+    let focused_row = if !app.currently_editing {
+        None
+    } else if app.editing_host.is_some() || !app.host_input.is_empty() {
+        input_row
+    } else if app.host_error.is_some() {
+        Some(visible_hosts.len() + 1)
+    } else {
+        visible_hosts.iter().position(|visible| *visible == app.selected_index)
+    };
+    app.hosts_page_size = usize::from(layout.hosts.height.saturating_sub(2));
+    // Selecting nothing through `select` would scroll back to the top
+    *app.hosts_list.selected_mut() = focused_row;
+    frame.render_stateful_widget(left_block, layout.hosts, &mut app.hosts_list);
+
+    let area = layout.hosts;
+    if app.filtering_hosts {
+        frame.set_cursor_position((area.x + 1 + hosts_title.chars().count() as u16, area.y));
+    } else if app.current_screen == CurrentScreen::Editing && app.pending_delete.is_none() {
+        let row = input_row.and_then(|row| row.checked_sub(app.hosts_list.offset())).filter(|row| *row < app.hosts_page_size);
+        if let Some(row) = row {
+            frame.set_cursor_position((area.x + 1 + ICON_WIDTH + app.host_cursor as u16, area.y + 1 + row as u16));
+        }
+    }
 
     let lines: Vec<Line> = vec![
         Line::from_iter([
-            Span::styled("Configured contribution target", Style::default().fg(Color::Yellow)),
+            Span::styled("Configured contribution target", theme.label),
             Span::raw(" : "),
-            Span::styled(app.contribution_goal.to_string(), Style::default().fg(Color::Green)),
+            Span::styled(app.contribution_goal.to_string(), theme.value),
         ]),
         Line::from_iter([
-            Span::styled("Current contribution count for today", Style::default().fg(Color::Yellow)),
+            Span::styled("Current contribution count for today", theme.label),
             Span::raw(" : "),
-            Span::styled(app.progress.to_string(), Style::default().fg(Color::Green)),
+            Span::styled(app.progress.to_string(), theme.value),
+            Span::styled(stale_progress_hint(app), theme.muted),
+            Span::styled(unconfirmed_progress_hint(app), theme.muted),
         ]),
         Line::from_iter([
-            Span::styled("Username", Style::default().fg(Color::Yellow)),
+            Span::styled("Username", theme.label),
             Span::raw(" : "),
-            Span::styled(app.username.to_string(), Style::default().fg(Color::Green)),
+            Span::styled(app.username.to_string(), theme.value),
         ]),
         Line::from_iter([
-            Span::styled("Previous date contribution goal met", Style::default().fg(Color::Yellow)),
+            Span::styled("Previous date contribution goal met", theme.label),
             Span::raw(" : "),
-            Span::styled(app.threshold_met_date.clone().unwrap_or("None".parse().unwrap()), Style::default().fg(Color::Green)),
+            Span::styled(app.threshold_met_date.clone().unwrap_or("None".parse().unwrap()), theme.value),
         ]),
         Line::from_iter([
-            Span::styled("Previous contribution goal met", Style::default().fg(Color::Yellow)),
+            Span::styled("Previous contribution goal met", theme.label),
             Span::raw(" : "),
-            Span::styled(app.threshold_met_goal.clone().unwrap_or(0).to_string(), Style::default().fg(Color::Green)),
+            Span::styled(app.threshold_met_goal.unwrap_or(0).to_string(), theme.value),
         ]),
     ].into_iter().collect();
 
@@ -117,35 +271,49 @@ pub fn ui(frame: &mut Frame, app: &App) {
             .borders(Borders::ALL)
             .title("Configuration")
         );
-    frame.render_widget(right_block, middle_chunks[1]);
+    frame.render_widget(right_block, layout.configuration);
+    frame.render_widget(build_status_panel(app), layout.status);
+
+    if let Some(diff) = layout.diff {
+        frame.render_widget(build_diff_panel(app), diff);
+    }
 
     let current_navigation_text = vec![
         // The first half of the text
         match app.current_screen {
-            CurrentScreen::Main => Span::styled("Normal Mode", Style::default().fg(Color::Green)),
-            CurrentScreen::Editing => Span::styled("Editing Mode", Style::default().fg(Color::Yellow)),
-            CurrentScreen::Configuration => Span::styled("Editing Mode", Style::default().fg(Color::Yellow)),
-            CurrentScreen::Exiting => Span::styled("Exiting", Style::default().fg(Color::LightRed)),
-            CurrentScreen::Help => Span::styled("Help", Style::default().fg(Color::Green)),
+            CurrentScreen::Main => Span::styled("Normal Mode", theme.good),
+            CurrentScreen::Editing => Span::styled("Editing Mode", theme.warning),
+            CurrentScreen::Configuration => Span::styled("Editing Mode", theme.warning),
+            CurrentScreen::Exiting => Span::styled("Exiting", theme.bad),
+            CurrentScreen::Help => Span::styled("Help", theme.good),
+            CurrentScreen::Token => Span::styled("Editing Mode", theme.warning),
+            CurrentScreen::Bundles => Span::styled("Editing Mode", theme.warning),
+            CurrentScreen::History => Span::styled("History", theme.good),
         }
             .to_owned(),
-        // A white divider bar to separate the two sections
-        Span::styled(" | ", Style::default().fg(Color::White)),
+        // A divider bar to separate the two sections
+        Span::styled(" | ", theme.text),
         // The final section of the text, with hints on what the user is editing
         {
-            if app.currently_editing {
-                Span::styled("Editing Host List", Style::default().fg(Color::Green))
+            if app.filtering_hosts {
+                Span::styled("Filtering Host List", theme.focused)
+            } else if app.currently_editing {
+                Span::styled("Editing Host List", theme.focused)
             } else if let Some(editing) = &app.editing_config_field {
                 match editing {
                     ContributionGoal => {
-                        Span::styled("Editing Contribution Goal", Style::default().fg(Color::Green))
+                        Span::styled("Editing Contribution Goal", theme.focused)
                     }
                     GithubUsername => {
-                        Span::styled("Editing Username", Style::default().fg(Color::LightGreen))
+                        Span::styled("Editing Username", theme.focused)
                     }
                 }
+            } else if app.editing_token_field.is_some() {
+                Span::styled("Editing GitHub Token", theme.focused)
+            } else if app.current_screen == CurrentScreen::Bundles {
+                Span::styled("Editing Bundles", theme.focused)
             } else {
-                Span::styled("Not Editing Anything", Style::default().fg(Color::DarkGray))
+                Span::styled("Not Editing Anything", theme.muted)
             }
         },
     ];
@@ -153,75 +321,46 @@ pub fn ui(frame: &mut Frame, app: &App) {
     let mode_footer = Paragraph::new(Line::from(current_navigation_text))
         .block(Block::default().borders(Borders::ALL));
 
-    let current_keys_hint = {
-        match app.current_screen {
-            CurrentScreen::Main => Span::styled(
-                "(i) modify hosts (c) edit configuration (q) quit (h) help",
-                Style::default().fg(Color::Red),
-            ),
-            CurrentScreen::Editing => Span::styled(
-                "(ESC) cancel (Tab) delete entry (Enter) save",
-                Style::default().fg(Color::Red),
-            ),
-            CurrentScreen::Exiting => Span::styled(
-                "(y/q) quit (n) resume",
-                Style::default().fg(Color::Red),
-            ),
-            CurrentScreen::Configuration => Span::styled(
-                "(ESC) cancel (Tab) switch panel (Enter) save",
-                Style::default().fg(Color::Red),
-            ),
-            CurrentScreen::Help => Span::styled(
-                "Press any key to return",
-                Style::default().fg(Color::Red),
-            )
-        }
-    };
+    let footer = footer_hints(app);
+    let current_keys_hint = Span::styled(
+        format!("{}{}{}", footer.prefix, app.keys.hints(&footer.hints), footer.suffix),
+        theme.key_hint,
+    );
 
     let key_notes_footer =
         Paragraph::new(Line::from(current_keys_hint)).block(Block::default().borders(Borders::ALL));
 
-    let footer_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(33), Constraint::Percentage(33), Constraint::Percentage(34)])
-        .split(chunks[2]);
-
-    let progress_bar_fg_color = if app.progress < app.contribution_goal / 2 {
-        Color::Red
-    } else if app.progress < app.contribution_goal {
-        Color::Yellow
+    // Local commits are included so that progress moves as soon as a commit is made
+    let progress = app.displayed_progress();
+    let progress_style = if progress < app.contribution_goal / 2 {
+        theme.bad
+    } else if progress < app.contribution_goal {
+        theme.warning
     } else {
-        Color::Green
+        theme.good
     };
 
     let progress_label = Span::styled(
-        format!("{:.1}/{:.1}", app.progress, app.contribution_goal),
-        Style::new().italic().bold().fg(progress_bar_fg_color),
+        format!("{:.1}/{:.1}{}{}", progress, app.contribution_goal,
+                if app.unconfirmed_progress() > 0 { " (unconfirmed)" } else { "" },
+                if app.progress_stale { " (stale)" } else { "" }),
+        progress_style.italic().bold(),
     );
 
-    let contribution_ratio = if app.progress >= app.contribution_goal {
+    let contribution_ratio = if progress >= app.contribution_goal {
         1.0
     } else {
-        app.progress as f64 / app.contribution_goal as f64
+        progress as f64 / app.contribution_goal as f64
     };
     let progress_bar = Gauge::default()
         .block(Block::bordered().title("Progress"))
-        .gauge_style(
-            Style::default()
-                .fg(progress_bar_fg_color)
-                .bg(Color::Black)
-                .add_modifier(Modifier::ITALIC),
-        )
+        .gauge_style(theme.gauge.patch(progress_style).add_modifier(Modifier::ITALIC))
+        .use_unicode(true)
         .label(progress_label)
         .ratio(contribution_ratio);
 
-    if let Some(_editing_config) = &app.editing_config_field {
-        let size = frame.area();
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(2)
-            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Length(3), Constraint::Min(0)].as_ref())
-            .split(size);
+    if app.editing_config_field.is_some() {
+        let [first_field, second_field, popup_status] = layout.popup_fields;
 
         let goal_input = Paragraph::new(app.contribution_goal_input.clone())
             .block(Block::default().borders(Borders::ALL).title("Contribution Goal"))
@@ -231,36 +370,73 @@ pub fn ui(frame: &mut Frame, app: &App) {
             .block(Block::default().borders(Borders::ALL).title("GitHub Username"))
             .style(get_input_field_style(app, GithubUsername));
 
-        frame.render_widget(Clear, chunks[1]);
-        frame.render_widget(Clear, chunks[2]);
-        frame.render_widget(goal_input, chunks[1]);
-        frame.render_widget(username_input, chunks[2]);
+        frame.render_widget(Clear, first_field);
+        frame.render_widget(Clear, second_field);
+        frame.render_widget(goal_input, first_field);
+        frame.render_widget(username_input, second_field);
+
+        if let Some(connection_status) = &app.connection_status {
+            let mut status = connection_status.clone();
+            if app.unverified_username.as_ref() == Some(&app.github_username_input) {
+                status.push_str(". Press (Enter) again to save anyway");
+            }
+            let connection = Paragraph::new(status)
+                .block(Block::default().borders(Borders::ALL).title("Connection"))
+                .style(theme.warning)
+                .wrap(Wrap { trim: true });
+            frame.render_widget(Clear, popup_status);
+            frame.render_widget(connection, popup_status);
+        }
     }
 
-    if let CurrentScreen::Help = app.current_screen {
-        frame.render_widget(Clear, frame.area()); //this clears the entire screen and anything already drawn
-        let popup_block = Block::default()
-            .title("Help")
-            .borders(Borders::ALL)
-            .style(Style::default().bg(Color::Black));
+    if app.editing_token_field.is_some() {
+        let [first_field, second_field, popup_status] = layout.popup_fields;
+
+        // Neither the token nor the passphrase are shown, only how much has been typed
+        let token_input = Paragraph::new(mask(&app.token_input))
+            .block(Block::default().borders(Borders::ALL).title("GitHub Token (leave empty to unlock)"))
+            .style(get_token_field_style(app, TokenField::Token));
+
+        let passphrase_input = Paragraph::new(mask(&app.passphrase_input))
+            .block(Block::default().borders(Borders::ALL).title("Passphrase"))
+            .style(get_token_field_style(app, TokenField::Passphrase));
+
+        let token_status = Paragraph::new(app.token_status.clone().unwrap_or_default())
+            .block(Block::default().borders(Borders::ALL).title("Token status"))
+            .style(theme.warning);
 
-        let help_text = Text::styled(HELP_SECTION_TEXT, Style::default().fg(Color::White));
-        let help_paragraph = Paragraph::new(help_text)
-            .block(popup_block);
-        let area = centered_rect(100, 100, frame.area());
-        frame.render_widget(help_paragraph, area);
+        for (widget, area) in [(token_input, first_field), (passphrase_input, second_field), (token_status, popup_status)] {
+            frame.render_widget(Clear, area);
+            frame.render_widget(widget, area);
+        }
+    }
+
+    if let CurrentScreen::Bundles = app.current_screen {
+        frame.render_widget(Clear, layout.bundles);
+        frame.render_widget(build_bundles_panel(app), layout.bundles);
+    }
+
+    if let CurrentScreen::History = app.current_screen {
+        let area = layout.title.union(layout.body);
+        frame.render_widget(Clear, area);
+        let history_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(8)])
+            .split(area);
+        frame.render_widget(build_history_panel(app, area.width), history_chunks[0]);
+        render_today_panel(frame, app, history_chunks[1]);
     }
 
     if let CurrentScreen::Exiting = app.current_screen {
         frame.render_widget(Clear, frame.area()); //this clears the entire screen and anything already drawn
         let popup_block = Block::default()
-            .title("Y/N")
+            .title(format!("{}/{}", app.keys.chord(Action::Confirm), app.keys.chord(Action::Cancel)))
             .borders(Borders::NONE)
-            .style(Style::default().bg(Color::DarkGray));
+            .style(theme.popup);
 
         let exit_text = Text::styled(
             "Exit?",
-            Style::default().fg(Color::Red),
+            theme.bad,
         );
         // the `trim: false` will stop the text from being cut off when over the edge of the block
         let exit_paragraph = Paragraph::new(exit_text)
@@ -271,19 +447,459 @@ pub fn ui(frame: &mut Frame, app: &App) {
         frame.render_widget(exit_paragraph, area);
     }
 
-    frame.render_widget(mode_footer, footer_chunks[0]);
-    frame.render_widget(key_notes_footer, footer_chunks[1]);
-    frame.render_widget(progress_bar, footer_chunks[2]);
+    frame.render_widget(mode_footer, layout.mode_footer);
+    frame.render_widget(key_notes_footer, layout.key_footer);
+    frame.render_widget(progress_bar, layout.progress);
+
+    if let CurrentScreen::Help = app.current_screen {
+        render_help(frame, app, &layout);
+    }
+}
+
+/// The keys which can be pressed on the current screen, with any text shown around them
+pub struct FooterHints {
+    pub prefix: String,
+    pub hints: Vec<(Action, &'static str)>,
+    pub suffix: &'static str,
+}
+
+pub fn footer_hints(app: &App) -> FooterHints {
+    let hints = |hints: Vec<(Action, &'static str)>| FooterHints { prefix: String::new(), hints, suffix: "" };
+    match app.current_screen {
+        CurrentScreen::Main if app.filtering_hosts => hints(vec![(Action::Save, "keep filter"), (Action::Cancel, "clear filter")]),
+        CurrentScreen::Main => hints(vec![
+            (Action::Insert, "modify hosts"),
+            (Action::Filter, "filter"),
+            (Action::Bundles, "bundles"),
+            (Action::Configuration, "edit configuration"),
+            (Action::Token, "token"),
+            (Action::Refresh, "refresh"),
+            (Action::History, "history"),
+            (Action::Undo, "undo"),
+            (Action::Redo, "redo"),
+            (Action::Quit, "quit"),
+            (Action::Help, "help"),
+        ]),
+        CurrentScreen::Editing => match app.pending_delete.and_then(|index| app.hosts.get(index)) {
+            Some(host) => FooterHints {
+                prefix: format!("Delete {}? ", host),
+                hints: vec![(Action::Confirm, "delete")],
+                suffix: " (any other key) keep",
+            },
+            None => hints(vec![
+                (Action::Cancel, "cancel"),
+                (Action::EditHost, "edit entry"),
+                (Action::DeleteHost, "delete entry"),
+                (Action::UndoUnsaved, "undo"),
+                (Action::Save, "save"),
+            ]),
+        },
+        CurrentScreen::Exiting => hints(vec![(Action::Confirm, "quit"), (Action::Cancel, "resume")]),
+        CurrentScreen::Configuration => hints(vec![
+            (Action::Cancel, "cancel"),
+            (Action::NextField, "switch panel"),
+            (Action::TestConnection, "test connection"),
+            (Action::Save, "save"),
+        ]),
+        CurrentScreen::Help => FooterHints {
+            prefix: String::new(),
+            hints: vec![(Action::NextField, "next page"), (Action::Cancel, "close")],
+            suffix: " (Left/Right) change page (Up/Down/PageUp/PageDown) scroll",
+        },
+        CurrentScreen::Token => hints(vec![(Action::Cancel, "cancel"), (Action::NextField, "switch field"), (Action::Save, "save/unlock")]),
+        CurrentScreen::Bundles => FooterHints {
+            prefix: String::new(),
+            hints: vec![(Action::Cancel, "close"), (Action::Help, "help")],
+            suffix: " (Space) enable/disable bundle",
+        },
+        CurrentScreen::History => hints(vec![(Action::Cancel, "close"), (Action::Help, "help"), (Action::Quit, "quit")]),
+    }
+}
+
+/// The help section whose tab is shown at the given column of the tabs, counted from the start of the tabs
+pub fn help_section_at(column: usize) -> Option<HelpSection> {
+    let mut start = 0;
+    for section in HelpSection::ALL {
+        // Each title is padded by a space either side, and followed by a divider
+        let end = start + section.title().chars().count() + 2;
+        if (start..end).contains(&column) {
+            return Some(section);
+        }
+        start = end + 1;
+    }
+    None
+}
+
+/// The action whose hint is shown at the given column of the footer, counted from the start of the text
+pub fn footer_action_at(app: &App, column: usize) -> Option<Action> {
+    let footer = footer_hints(app);
+    let column = column.checked_sub(footer.prefix.chars().count())?;
+    app.keys.hint_at(&footer.hints, column)
+}
+
+/// Draws the help section with its keys, scrolled to where it's been scrolled to, along with tabs for the other sections
+fn render_help(frame: &mut Frame, app: &mut App, layout: &ScreenLayout) {
+    let theme = &app.theme;
+    frame.render_widget(Clear, layout.help);
+    frame.render_widget(Block::default()
+        .title("Help")
+        .borders(Borders::ALL)
+        .style(theme.popup), layout.help);
+
+    let titles = HelpSection::ALL.map(HelpSection::title);
+    let selected = HelpSection::ALL.iter().position(|section| *section == app.help_section);
+    frame.render_widget(Tabs::new(titles)
+        .select(selected)
+        .style(theme.muted)
+        .highlight_style(theme.selected), layout.help_tabs);
+
+    // Text is wrapped here rather than by the paragraph, so that it's known how far it can be scrolled. A column is left
+    // for the scrollbar
+    let width = usize::from(layout.help_body.width.saturating_sub(2));
+    let mut lines = Vec::new();
+    for paragraph in app.help_section.paragraphs() {
+        lines.extend(help::wrap(paragraph, width).into_iter().map(|line| Line::styled(line, theme.text)));
+        lines.push(Line::default());
+    }
+    let key_table = app.help_section.key_table(&app.keys);
+    if !key_table.is_empty() {
+        lines.push(Line::styled("Keys", theme.heading));
+        let key_width = key_table.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+        for (key, description) in key_table {
+            lines.push(Line::from_iter([
+                Span::styled(format!("  {: <1$}  ", key, key_width), theme.key_hint),
+                Span::styled(description, theme.text),
+            ]));
+        }
+    }
+
+    let page_size = usize::from(layout.help_body.height);
+    let max_scroll = lines.len().saturating_sub(page_size);
+    app.help_page_size = page_size;
+    app.help_scroll = app.help_scroll.min(max_scroll);
+
+    frame.render_widget(Paragraph::new(lines).scroll((app.help_scroll as u16, 0)), layout.help_body);
+    if max_scroll > 0 {
+        let mut scrollbar_state = ScrollbarState::new(max_scroll).position(app.help_scroll);
+        frame.render_stateful_widget(Scrollbar::new(ScrollbarOrientation::VerticalRight), layout.help_body, &mut scrollbar_state);
+    }
+}
+
+/// Whether the hosts are blocked in the hosts file, which is left blank until the file has been read
+fn enforcement_icon<'a>(app: &App, hosts: impl IntoIterator<Item = &'a str>) -> Span<'static> {
+    let theme = &app.theme;
+    match app.enforcement_of(hosts) {
+        Some(Enforcement::Enforced) => Span::styled(BLOCKED_ICON, theme.bad),
+        Some(Enforcement::Relaxed) => Span::styled(UNBLOCKED_ICON, theme.good),
+        Some(Enforcement::Inconsistent) => Span::styled(INCONSISTENT_ICON, theme.warning),
+        None => Span::raw(NO_ICON),
+    }
+}
+
+fn stale_progress_hint(app: &App) -> String {
+    match app.last_fetch {
+        Some(fetched_at) if app.progress_stale => format!(" (stale, last updated {})", fetched_at.format("%H:%M")),
+        _ => String::new(),
+    }
+}
+
+fn unconfirmed_progress_hint(app: &App) -> String {
+    match app.unconfirmed_progress() {
+        0 => String::new(),
+        unconfirmed => format!(" (+{} local, waiting for GitHub)", unconfirmed),
+    }
+}
+
+fn build_status_panel(app: &App) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let (block_state, block_state_style) = match app.block_state() {
+        Some(BlockState::Blocked) => ("Blocked", theme.bad),
+        Some(BlockState::Unblocked) => ("Unblocked", theme.good),
+        Some(BlockState::PartiallyBlocked) => ("Partially blocked", theme.warning),
+        Some(BlockState::NotConfigured) => ("No hosts configured", theme.muted),
+        None => ("Unknown", theme.muted),
+    };
+
+    let last_fetch = app.last_fetch
+        .map(|fetched_at| fetched_at.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or("Never".to_string());
+
+    let next_poll = app.next_poll_at
+        .map(|next_poll_at| next_poll_at.format("%H:%M:%S").to_string())
+        .unwrap_or("Unknown".to_string());
+
+    let (rate_limit, rate_limit_style) = match &app.rate_limit {
+        Some(rate_limit) => (
+            format!("{}/{} (resets {})", rate_limit.remaining, rate_limit.limit, rate_limit.reset_at.with_timezone(&Local).format("%H:%M")),
+            if rate_limit.remaining * 10 < rate_limit.limit { theme.bad } else { theme.good },
+        ),
+        None => ("Unknown".to_string(), theme.muted),
+    };
+
+    let mut lines = vec![
+        Line::from_iter([
+            Span::styled("Block state", theme.label),
+            Span::raw(" : "),
+            Span::styled(block_state, block_state_style),
+        ]),
+        Line::from_iter([
+            Span::styled("Last successful fetch", theme.label),
+            Span::raw(" : "),
+            Span::styled(last_fetch, theme.value),
+        ]),
+        Line::from_iter([
+            Span::styled("Next check", theme.label),
+            Span::raw(" : "),
+            Span::styled(next_poll, theme.value),
+        ]),
+        Line::from_iter([
+            Span::styled("API quota remaining", theme.label),
+            Span::raw(" : "),
+            Span::styled(rate_limit, rate_limit_style),
+        ]),
+        Line::from_iter([
+            Span::styled("GitHub token", theme.label),
+            Span::raw(" : "),
+            Span::styled(app.token_status.clone().unwrap_or("Unknown".to_string()), theme.value),
+        ]),
+        status_line(theme, "GitHub API", &app.api_error),
+        status_line(theme, "Hosts file", &app.hosts_error),
+    ];
+    if app.last_error.is_some() {
+        lines.push(status_line(theme, "Error", &app.last_error));
+    }
+
+    Paragraph::new(lines)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title("Status"))
+}
+
+fn status_line<'a>(theme: &Theme, label: &'a str, error: &Option<StatusMessage>) -> Line<'a> {
+    let status = match error {
+        Some(error) => Span::styled(
+            format!("{} ({})", error.message, error.at.format("%H:%M:%S")),
+            theme.bad,
+        ),
+        None => Span::styled("OK", theme.good),
+    };
+
+    Line::from_iter([
+        Span::styled(label, theme.label),
+        Span::raw(" : "),
+        status,
+    ])
+}
+
+fn build_bundles_panel(app: &App) -> List<'_> {
+    let items: Vec<ListItem> = BUNDLES.iter().enumerate().map(|(index, (name, hosts))| {
+        let enabled = app.bundles.iter().any(|bundle| bundle == name);
+        let style = if index == app.selected_bundle {
+            app.theme.selected
+        } else if enabled {
+            app.theme.host
+        } else {
+            app.theme.text
+        };
+        ListItem::new(Line::from(Span::styled(
+            format!("[{}] {: <10} {} hosts", if enabled { "x" } else { " " }, name, hosts.len()),
+            style,
+        )))
+    }).collect();
+
+    List::new(items)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title("Bundles"))
+}
+
+fn build_history_panel(app: &App, width: u16) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let mut lines = Vec::new();
+    if let Some(calendar) = &app.calendar {
+        // Each day takes up two columns, with a gap between them, unless there isn't room
+        let cell_width = if usize::from(width) >= HEATMAP_LABEL_WIDTH + 2 + calendar.len().div_ceil(7) * 2 { 2 } else { 1 };
+        lines.extend(heatmap_lines(theme, calendar, &app.goal_met_days, cell_width));
+
+        let total: u32 = calendar.iter().map(|day| day.contribution_count).sum();
+        let goal_met = calendar.iter().filter(|day| app.goal_met_days.contains(&day.date)).count();
+        lines.push(Line::raw(""));
+        let mut legend = vec![Span::raw(" ".repeat(HEATMAP_LABEL_WIDTH)), Span::styled("Less ", theme.muted)];
+        legend.extend(theme.heatmap.iter().map(|style| Span::styled(format!("{} ", HEATMAP_DAY), *style)));
+        legend.push(Span::styled("More   ", theme.muted));
+        legend.push(Span::styled(format!("{} goal met, hosts unlocked", HEATMAP_GOAL_MET_DAY), theme.muted));
+        lines.push(Line::from(legend));
+        lines.push(Line::raw(""));
+        lines.push(Line::from(Span::styled(
+            format!("{}{} contributions in the last year, goal met on {} days", " ".repeat(HEATMAP_LABEL_WIDTH), total, goal_met),
+            theme.label,
+        )));
+    }
+    if let Some(status) = &app.calendar_status {
+        lines.push(Line::from(Span::styled(status.clone(), theme.warning)));
+    }
+
+    Paragraph::new(lines)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title("Contribution history"))
+}
+
+/// Today's progress as a bar per interval, with when the hosts tend to be unlocked underneath
+fn render_today_panel(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Today");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let today_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Length(1), Constraint::Min(0)])
+        .split(inner);
+    frame.render_widget(Sparkline::default()
+        .data(&app.progress_timeline)
+        .style(app.theme.good), today_chunks[0]);
+
+    // The bars cover half an hour each, so the axis is labelled every six hours
+    if let Some(started_at) = app.progress_started_at {
+        let axis: String = (0..4)
+            .map(|quarter| format!("{:<12}", (started_at + TimeDelta::hours(quarter * 6)).format("%H:%M")))
+            .collect();
+        frame.render_widget(Paragraph::new(Span::styled(axis, app.theme.muted)), today_chunks[1]);
+    }
+
+    if let Some(stats) = &app.unlock_stats {
+        frame.render_widget(Paragraph::new(unlock_stats_lines(&app.theme, stats)), today_chunks[2]);
+    }
+}
+
+fn unlock_stats_lines<'a>(theme: &Theme, stats: &UnlockStats) -> Vec<Line<'a>> {
+    let goal_met = match stats.goal_met_percent() {
+        Some(percent) => format!("Goal met on {:.0}% of {} tracked days", percent, stats.tracked_days),
+        None => String::from("No days tracked yet"),
+    };
+    let mut unlock_times = vec![Span::styled("Median unlock ", theme.muted)];
+    for (weekday, time) in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"].iter().zip(stats.median_unlock_times) {
+        unlock_times.push(Span::styled(format!(" {} ", weekday), theme.muted));
+        unlock_times.push(Span::raw(time.map_or(String::from("--:--"), |time| time.format("%H:%M").to_string())));
+    }
+    vec![Line::from(Span::styled(goal_met, theme.label)), Line::from(unlock_times)]
+}
+
+/// Lays the days out in a column per week, starting on Sunday, with the months labelled along the top
+fn heatmap_lines<'a>(theme: &Theme, calendar: &[CalendarDay], goal_met_days: &BTreeSet<NaiveDate>, cell_width: usize) -> Vec<Line<'a>> {
+    let Some(first_day) = calendar.first() else {
+        return Vec::new();
+    };
+    let first_sunday = first_day.date - chrono::Days::new(u64::from(first_day.date.weekday().num_days_from_sunday()));
+    let weeks = calendar.iter()
+        .map(|day| (day.date - first_sunday).num_days() as usize / 7 + 1)
+        .max()
+        .unwrap_or_default();
+    let max_count = calendar.iter().map(|day| day.contribution_count).max().unwrap_or_default();
+
+    let mut grid: Vec<Vec<Option<&CalendarDay>>> = vec![vec![None; weeks]; 7];
+    for day in calendar {
+        let week = (day.date - first_sunday).num_days() as usize / 7;
+        grid[day.date.weekday().num_days_from_sunday() as usize][week] = Some(day);
+    }
+
+    // A month is labelled above the first week which starts in it, as long as there's room for the label
+    let mut months = String::new();
+    let mut last_month = None;
+    for week in 0..weeks {
+        let month = (first_sunday + chrono::Days::new(week as u64 * 7)).month0() as usize;
+        let position = HEATMAP_LABEL_WIDTH + week * cell_width;
+        if last_month != Some(month) && months.len() < position {
+            months.push_str(&" ".repeat(position - months.len()));
+            months.push_str(MONTHS[month]);
+        }
+        last_month = Some(month);
+    }
+
+    let mut lines = vec![Line::from(Span::styled(months, theme.muted))];
+    for (weekday, row) in grid.iter().enumerate() {
+        let label = match weekday {
+            1 => "Mon",
+            3 => "Wed",
+            5 => "Fri",
+            _ => "",
+        };
+        let mut spans = vec![Span::styled(format!("{:<width$}", label, width = HEATMAP_LABEL_WIDTH), theme.muted)];
+        for day in row {
+            let cell = match day {
+                Some(day) => Span::styled(
+                    format!("{:<cell_width$}", if goal_met_days.contains(&day.date) { HEATMAP_GOAL_MET_DAY } else { HEATMAP_DAY }),
+                    theme.heatmap[contribution_level(day.contribution_count, max_count)],
+                ),
+                None => Span::raw(" ".repeat(cell_width)),
+            };
+            spans.push(cell);
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// Which of the heatmap colours to use, relative to the busiest day
+fn contribution_level(contribution_count: u32, max_count: u32) -> usize {
+    if contribution_count == 0 || max_count == 0 {
+        0
+    } else {
+        (contribution_count * 4).div_ceil(max_count).clamp(1, 4) as usize
+    }
+}
+
+fn build_diff_panel(app: &App) -> Paragraph<'_> {
+    let theme = &app.theme;
+    let diff_lines: Vec<Line> = match &app.hosts_diff {
+        Some(diff) => diff.lines().map(|line| {
+            let style = if line.starts_with("+++") || line.starts_with("---") {
+                theme.heading
+            } else if line.starts_with('+') {
+                theme.good
+            } else if line.starts_with('-') {
+                theme.bad
+            } else if line.starts_with("@@") {
+                theme.accent
+            } else {
+                theme.muted
+            };
+            Line::from(Span::styled(line.to_string(), style))
+        }).collect(),
+        None => vec![Line::from(Span::styled(
+            "No changes to the hosts file yet",
+            theme.muted,
+        ))],
+    };
+
+    Paragraph::new(diff_lines)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title("Hosts file changes (dry run)"))
 }
 
 fn get_input_field_style(app: &App, field: EditingConfigField) -> Style {
     if app.editing_config_field == Some(field) {
-        Style::default().fg(Color::Green)
+        app.theme.focused
+    } else {
+        app.theme.text
+    }
+}
+
+fn get_token_field_style(app: &App, field: TokenField) -> Style {
+    if app.editing_token_field == Some(field) {
+        app.theme.focused
     } else {
-        Style::default().fg(Color::White)
+        app.theme.text
     }
 }
 
+fn mask(input: &str) -> String {
+    "*".repeat(input.chars().count())
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -306,6 +922,8 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 
 #[cfg(test)]
 mod tests {
+    use ratatui::style::Color;
+
     use super::*;
 
     #[test]
@@ -317,7 +935,7 @@ mod tests {
         let threshold_met_date = Some(String::from("01/11/2024"));
         let threshold_met_goal = Some(4);
 
-        let mut app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal.clone());
+        let mut app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal);
         app.editing_config_field = Option::from(ContributionGoal);
 
         let actual_style = get_input_field_style(&app, ContributionGoal);
@@ -334,10 +952,36 @@ mod tests {
         let threshold_met_date = Some(String::from("01/11/2024"));
         let threshold_met_goal = Some(4);
 
-        let app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal.clone());
+        let app = App::new(hosts.clone(), current_contributions, contribution_goal, username.clone(), threshold_met_date.clone(), threshold_met_goal);
 
         let actual_style = get_input_field_style(&app, ContributionGoal);
 
         assert_eq!(actual_style, Style::default().fg(Color::White));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn can_get_contribution_level() {
+        assert_eq!(contribution_level(0, 8), 0);
+        assert_eq!(contribution_level(1, 8), 1);
+        assert_eq!(contribution_level(5, 8), 3);
+        assert_eq!(contribution_level(8, 8), 4);
+    }
+
+    #[test]
+    fn heatmap_lays_out_weeks_from_sunday() {
+        // 2024-10-26 was a Saturday, so the following days start a new week
+        let calendar: Vec<CalendarDay> = (0..3).map(|offset| CalendarDay {
+            date: NaiveDate::from_ymd_opt(2024, 10, 26).unwrap() + chrono::Days::new(offset),
+            contribution_count: offset as u32,
+        }).collect();
+        let goal_met_days = BTreeSet::from([NaiveDate::from_ymd_opt(2024, 10, 28).unwrap()]);
+
+        let lines = heatmap_lines(&Theme::default(), &calendar, &goal_met_days, 2);
+
+        let text: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        assert_eq!(text[0], "    Oct");
+        assert_eq!(text[1], "      ■ ");
+        assert_eq!(text[2], "Mon   ◆ ");
+        assert_eq!(text[7], "    ■   ");
+    }
+}